@@ -0,0 +1,28 @@
+use rust_code::{Doubling, Exact, GrowthPolicy, Instrumented, OneAndHalf, PageRounded, Vec};
+use std::time::SystemTime;
+
+const TOTAL_PUSH: u64 = 1000000;
+
+fn run<G: GrowthPolicy>(name: &str, growth: G) {
+    let mut vec = Vec::with_growth(Instrumented::new(growth));
+    let begin = SystemTime::now();
+    for i in 0..TOTAL_PUSH {
+        vec.push(i);
+    }
+    let dur = begin.elapsed().unwrap();
+    println!(
+        "{:<12} len={} cap={} {:?} in {:?}",
+        name,
+        vec.len(),
+        vec.capacity(),
+        vec.growth().stats(),
+        dur
+    );
+}
+
+fn main() {
+    run("Doubling", Doubling);
+    run("OneAndHalf", OneAndHalf);
+    run("Exact", Exact);
+    run("PageRounded", PageRounded::new());
+}
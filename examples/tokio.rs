@@ -1,3 +1,5 @@
+use rust_code::{channel, LockFreeQueue};
+use std::sync::Arc;
 use std::time::SystemTime;
 
 const TOTAL_ITER: u128 = 100000;
@@ -60,6 +62,51 @@ async fn bench_mpmc() {
     println!("\tmpsc 4byte {:5}ns", duration.as_nanos() / total);
 }
 
+// same as bench_mpmc, with our own channel
+async fn bench_channel() {
+    let begin = SystemTime::now();
+    let total = TOTAL_ITER;
+    let (send, recv) = channel::unbounded();
+    tokio::task::spawn(async move {
+        for i in 0..total {
+            send.send(i).await.unwrap();
+            tokio::task::yield_now().await;
+        }
+    });
+    for i in 0..total {
+        assert_eq!(recv.recv().await.unwrap(), i);
+    }
+    let duration = begin.elapsed().unwrap();
+    println!("\tchannel 4byte {:5}ns", duration.as_nanos() / total);
+}
+
+// same as bench_mpmc, with the lock-free queue polled by the receiver
+async fn bench_lockfree() {
+    let begin = SystemTime::now();
+    let total = TOTAL_ITER;
+    let queue = Arc::new(LockFreeQueue::new());
+    let send = queue.clone();
+    tokio::task::spawn(async move {
+        for i in 0..total {
+            send.enqueue(i);
+            tokio::task::yield_now().await;
+        }
+    });
+    for i in 0..total {
+        loop {
+            match queue.dequeue() {
+                Some(val) => {
+                    assert_eq!(val, i);
+                    break;
+                }
+                None => tokio::task::yield_now().await,
+            }
+        }
+    }
+    let duration = begin.elapsed().unwrap();
+    println!("\tlock-free 4byte {:5}ns", duration.as_nanos() / total);
+}
+
 fn runtime(t: Thread, f: Feature) -> tokio::runtime::Runtime {
     let mut builder = match t {
         Thread::Current => {
@@ -102,6 +149,12 @@ fn main() {
             runtime(thread, feature).block_on(async move {
                 tokio::task::spawn(bench_mpmc()).await.unwrap();
             });
+            runtime(thread, feature).block_on(async move {
+                tokio::task::spawn(bench_channel()).await.unwrap();
+            });
+            runtime(thread, feature).block_on(async move {
+                tokio::task::spawn(bench_lockfree()).await.unwrap();
+            });
         }
     }
     println!("    spawn & yield:");
@@ -116,6 +169,8 @@ fn main() {
     for thread in threads {
         for feature in features {
             runtime(thread, feature).block_on(bench_mpmc());
+            runtime(thread, feature).block_on(bench_channel());
+            runtime(thread, feature).block_on(bench_lockfree());
         }
     }
     println!("   yield & spawn:");
@@ -1,4 +1,14 @@
 #![allow(dead_code)]
 mod algorithm;
 mod lists;
+mod ptr_kind;
 mod vec;
+
+// used by the benchmarks in examples/tokio.rs
+pub use lists::channel;
+pub use lists::lockfree::Queue as LockFreeQueue;
+
+// used by examples/growth.rs
+pub use vec::{
+    Doubling, Exact, GrowthPolicy, GrowthStats, Instrumented, OneAndHalf, PageRounded, Vec,
+};
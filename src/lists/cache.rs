@@ -0,0 +1,606 @@
+use super::linked_list::{LinkedList, NodeRef};
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash, Hasher};
+
+// The map keys point at the keys stored in the list nodes, so K is stored
+// once. An entry is removed from the map before its node is freed.
+struct KeyRef<K>(*const K);
+
+impl<K: Hash> Hash for KeyRef<K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        unsafe { (*self.0).hash(state) }
+    }
+}
+
+impl<K: PartialEq> PartialEq for KeyRef<K> {
+    fn eq(&self, other: &Self) -> bool {
+        unsafe { *self.0 == *other.0 }
+    }
+}
+
+impl<K: Eq> Eq for KeyRef<K> {}
+
+impl<K> Borrow<K> for KeyRef<K> {
+    fn borrow(&self) -> &K {
+        unsafe { &*self.0 }
+    }
+}
+
+type Weigher<K, V> = Box<dyn Fn(&K, &V) -> usize>;
+type OnEvict<K, V> = Box<dyn FnMut(K, V)>;
+
+// Least recently used cache: a hash index into a list ordered by use, the
+// most recent at the front. Entries are evicted from the back when there are
+// more than `cap` of them, or their total weight is above `max_weight`.
+pub struct LruCache<K, V, S = RandomState> {
+    map: HashMap<KeyRef<K>, NodeRef<(K, V)>, S>,
+    list: LinkedList<(K, V)>,
+    cap: usize,
+    weigher: Option<Weigher<K, V>>,
+    max_weight: usize,
+    weight: usize,
+    on_evict: Option<OnEvict<K, V>>,
+}
+
+impl<K: Hash + Eq, V> LruCache<K, V> {
+    pub fn new(cap: usize) -> Self {
+        Self::with_hasher(cap, RandomState::new())
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> LruCache<K, V, S> {
+    pub fn with_hasher(cap: usize, hasher: S) -> Self {
+        Self {
+            map: HashMap::with_hasher(hasher),
+            list: LinkedList::new(),
+            cap,
+            weigher: None,
+            max_weight: usize::MAX,
+            weight: 0,
+            on_evict: None,
+        }
+    }
+
+    // limit the total weight too, e.g. the bytes held by the entries
+    pub fn with_weigher(
+        mut self,
+        max_weight: usize,
+        weigher: impl Fn(&K, &V) -> usize + 'static,
+    ) -> Self {
+        self.weight = self.list.iter().map(|(k, v)| weigher(k, v)).sum();
+        self.weigher = Some(Box::new(weigher));
+        self.max_weight = max_weight;
+        self.evict();
+        self
+    }
+
+    // called with the entries dropped by the limits, not with those removed
+    // by pop or replaced by put
+    pub fn on_evict(mut self, on_evict: impl FnMut(K, V) + 'static) -> Self {
+        self.on_evict = Some(Box::new(on_evict));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn cap(&self) -> usize {
+        self.cap
+    }
+
+    pub fn weight(&self) -> usize {
+        self.weight
+    }
+
+    fn weigh(&self, key: &K, val: &V) -> usize {
+        self.weigher.as_ref().map_or(0, |weigher| weigher(key, val))
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    // get without marking the entry as used
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        let node = *self.map.get(key)?;
+        unsafe { Some(&node.get().1) }
+    }
+
+    fn touch(&mut self, key: &K) -> Option<NodeRef<(K, V)>> {
+        let node = *self.map.get(key)?;
+        unsafe {
+            self.list.unlink(node);
+            self.list.link_front(node);
+        }
+        Some(node)
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let node = self.touch(key)?;
+        unsafe { Some(&node.get().1) }
+    }
+
+    // the weight is not updated, use put to change the size of a value
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let node = self.touch(key)?;
+        unsafe { Some(&mut node.get_mut().1) }
+    }
+
+    // insert or replace, returns the old value. The new entry is evicted
+    // right away if it alone is heavier than max_weight.
+    pub fn put(&mut self, key: K, val: V) -> Option<V> {
+        let weight = self.weigh(&key, &val);
+        let old = match self.touch(&key) {
+            Some(node) => {
+                let old = std::mem::replace(unsafe { &mut node.get_mut().1 }, val);
+                self.weight -= self.weigh(&key, &old);
+                Some(old)
+            }
+            None => {
+                let node = self.list.push_front_ref((key, val));
+                let key = unsafe { &node.get().0 };
+                self.map.insert(KeyRef(key), node);
+                None
+            }
+        };
+        self.weight += weight;
+        self.evict();
+        old
+    }
+
+    pub fn pop(&mut self, key: &K) -> Option<V> {
+        let node = self.map.remove(key)?;
+        let (key, val) = unsafe { self.list.remove(node) };
+        self.weight -= self.weigh(&key, &val);
+        Some(val)
+    }
+
+    pub fn peek_lru(&self) -> Option<(&K, &V)> {
+        let node = self.list.back_ref()?;
+        let (key, val) = unsafe { node.get() };
+        Some((key, val))
+    }
+
+    pub fn pop_lru(&mut self) -> Option<(K, V)> {
+        let node = self.list.back_ref()?;
+        self.map.remove(unsafe { &node.get().0 });
+        let (key, val) = unsafe { self.list.remove(node) };
+        self.weight -= self.weigh(&key, &val);
+        Some((key, val))
+    }
+
+    pub fn resize(&mut self, cap: usize) {
+        self.cap = cap;
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.len() > self.cap || self.weight > self.max_weight {
+            let (key, val) = self.pop_lru().unwrap();
+            if let Some(on_evict) = &mut self.on_evict {
+                on_evict(key, val);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.list.clear();
+        self.weight = 0;
+    }
+
+    // most recently used first
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&K, &V)> {
+        self.list.iter().map(|(key, val)| (key, val))
+    }
+}
+
+struct LfuEntry<K, V> {
+    key: K,
+    val: V,
+    bucket: NodeRef<Bucket<K, V>>,
+}
+
+// the entries used the same number of times, most recent at the front
+struct Bucket<K, V> {
+    uses: u64,
+    entries: LinkedList<LfuEntry<K, V>>,
+}
+
+// Least frequently used cache. Entries are kept in one list per use count,
+// and the lists in a list ordered by count, so finding the fewest uses and
+// bumping a count are O(1). Ties are broken by recency, the least recently
+// used of the least used entries is evicted first.
+pub struct LfuCache<K, V, S = RandomState> {
+    map: HashMap<KeyRef<K>, NodeRef<LfuEntry<K, V>>, S>,
+    // fewest uses at the front, empty buckets are removed
+    buckets: LinkedList<Bucket<K, V>>,
+    cap: usize,
+    weigher: Option<Weigher<K, V>>,
+    max_weight: usize,
+    weight: usize,
+    on_evict: Option<OnEvict<K, V>>,
+}
+
+impl<K: Hash + Eq, V> LfuCache<K, V> {
+    pub fn new(cap: usize) -> Self {
+        Self::with_hasher(cap, RandomState::new())
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> LfuCache<K, V, S> {
+    pub fn with_hasher(cap: usize, hasher: S) -> Self {
+        Self {
+            map: HashMap::with_hasher(hasher),
+            buckets: LinkedList::new(),
+            cap,
+            weigher: None,
+            max_weight: usize::MAX,
+            weight: 0,
+            on_evict: None,
+        }
+    }
+
+    // limit the total weight too, e.g. the bytes held by the entries
+    pub fn with_weigher(
+        mut self,
+        max_weight: usize,
+        weigher: impl Fn(&K, &V) -> usize + 'static,
+    ) -> Self {
+        self.weight = self
+            .map
+            .values()
+            .map(|node| {
+                let entry = unsafe { node.get() };
+                weigher(&entry.key, &entry.val)
+            })
+            .sum();
+        self.weigher = Some(Box::new(weigher));
+        self.max_weight = max_weight;
+        self.evict_to(self.cap, max_weight);
+        self
+    }
+
+    pub fn on_evict(mut self, on_evict: impl FnMut(K, V) + 'static) -> Self {
+        self.on_evict = Some(Box::new(on_evict));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn cap(&self) -> usize {
+        self.cap
+    }
+
+    pub fn weight(&self) -> usize {
+        self.weight
+    }
+
+    fn weigh(&self, key: &K, val: &V) -> usize {
+        self.weigher.as_ref().map_or(0, |weigher| weigher(key, val))
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        let node = *self.map.get(key)?;
+        unsafe { Some(&node.get().val) }
+    }
+
+    // times the entry was used, put counts as a use
+    pub fn uses(&self, key: &K) -> Option<u64> {
+        let node = *self.map.get(key)?;
+        unsafe { Some(node.get().bucket.get().uses) }
+    }
+
+    // take the node out of its bucket, and drop the bucket if it is empty now
+    unsafe fn unlink(&mut self, node: NodeRef<LfuEntry<K, V>>) {
+        let bucket = node.get().bucket;
+        let entries = &mut bucket.get_mut().entries;
+        entries.unlink(node);
+        if entries.is_empty() {
+            self.buckets.remove(bucket);
+        }
+    }
+
+    fn touch(&mut self, key: &K) -> Option<NodeRef<LfuEntry<K, V>>> {
+        let node = *self.map.get(key)?;
+        unsafe {
+            let bucket = node.get().bucket;
+            let uses = bucket.get().uses + 1;
+            // the bucket for one more use is next, if there is one
+            let next = match self.buckets.next_ref(bucket) {
+                Some(next) if next.get().uses == uses => next,
+                _ => self.buckets.push_after_ref(
+                    bucket,
+                    Bucket {
+                        uses,
+                        entries: LinkedList::new(),
+                    },
+                ),
+            };
+            self.unlink(node);
+            next.get_mut().entries.link_front(node);
+            node.get_mut().bucket = next;
+        }
+        Some(node)
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let node = self.touch(key)?;
+        unsafe { Some(&node.get().val) }
+    }
+
+    // the weight is not updated, use put to change the size of a value
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let node = self.touch(key)?;
+        unsafe { Some(&mut node.get_mut().val) }
+    }
+
+    // insert or replace, returns the old value. The new entry is evicted
+    // right away if it alone is heavier than max_weight.
+    pub fn put(&mut self, key: K, val: V) -> Option<V> {
+        let weight = self.weigh(&key, &val);
+        if let Some(node) = self.touch(&key) {
+            let old = std::mem::replace(unsafe { &mut node.get_mut().val }, val);
+            self.weight = self.weight - self.weigh(&key, &old) + weight;
+            self.evict_to(self.cap, self.max_weight);
+            return Some(old);
+        }
+        if self.cap == 0 || weight > self.max_weight {
+            if let Some(on_evict) = &mut self.on_evict {
+                on_evict(key, val);
+            }
+            return None;
+        }
+
+        // make room first, so the new entry is not the one evicted
+        self.evict_to(self.cap - 1, self.max_weight - weight);
+        let bucket = match self.buckets.front_ref() {
+            Some(front) if unsafe { front.get().uses } == 1 => front,
+            _ => self.buckets.push_front_ref(Bucket {
+                uses: 1,
+                entries: LinkedList::new(),
+            }),
+        };
+        let entry = LfuEntry { key, val, bucket };
+        let node = unsafe { bucket.get_mut().entries.push_front_ref(entry) };
+        self.map.insert(KeyRef(unsafe { &node.get().key }), node);
+        self.weight += weight;
+        None
+    }
+
+    pub fn pop(&mut self, key: &K) -> Option<V> {
+        let node = self.map.remove(key)?;
+        unsafe { self.unlink(node) };
+        let entry = unsafe { node.into_inner() };
+        self.weight -= self.weigh(&entry.key, &entry.val);
+        Some(entry.val)
+    }
+
+    pub fn peek_lfu(&self) -> Option<(&K, &V)> {
+        let bucket = self.buckets.front_ref()?;
+        let node = unsafe { bucket.get().entries.back_ref()? };
+        let entry = unsafe { node.get() };
+        Some((&entry.key, &entry.val))
+    }
+
+    pub fn pop_lfu(&mut self) -> Option<(K, V)> {
+        let bucket = self.buckets.front_ref()?;
+        let node = unsafe { bucket.get().entries.back_ref()? };
+        self.map.remove(unsafe { &node.get().key });
+        unsafe { self.unlink(node) };
+        let entry = unsafe { node.into_inner() };
+        self.weight -= self.weigh(&entry.key, &entry.val);
+        Some((entry.key, entry.val))
+    }
+
+    pub fn resize(&mut self, cap: usize) {
+        self.cap = cap;
+        self.evict_to(cap, self.max_weight);
+    }
+
+    fn evict_to(&mut self, len: usize, weight: usize) {
+        while self.len() > len || self.weight > weight {
+            let (key, val) = self.pop_lfu().unwrap();
+            if let Some(on_evict) = &mut self.on_evict {
+                on_evict(key, val);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.buckets.clear();
+        self.weight = 0;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LfuCache, LruCache};
+    use rand::random;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn lru() {
+        let mut cache = LruCache::new(2);
+        assert_eq!(cache.put(1, "a"), None);
+        assert_eq!(cache.put(2, "b"), None);
+        assert_eq!(cache.get(&1), Some(&"a"));
+        // 2 is the least recently used now
+        assert_eq!(cache.put(3, "c"), None);
+        assert!(!cache.contains(&2));
+        assert_eq!(cache.peek(&1), Some(&"a"));
+        assert_eq!(cache.peek_lru(), Some((&1, &"a")));
+        assert_eq!(cache.put(3, "d"), Some("c"));
+        assert!(cache.iter().eq(vec![(&3, &"d"), (&1, &"a")]));
+        *cache.get_mut(&1).unwrap() = "e";
+        assert_eq!(cache.pop_lru(), Some((3, "d")));
+        assert_eq!(cache.pop(&1), Some("e"));
+        assert_eq!(cache.pop(&1), None);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn lru_limits() {
+        let evicted = Rc::new(RefCell::new(Vec::new()));
+        let log = evicted.clone();
+        let mut cache = LruCache::new(10)
+            .with_weigher(100, |_: &u32, val: &String| val.len())
+            .on_evict(move |key, _| log.borrow_mut().push(key));
+
+        for i in 0..10 {
+            cache.put(i, "x".repeat(10));
+        }
+        assert_eq!(cache.weight(), 100);
+        cache.get(&0);
+        cache.put(10, "x".repeat(25));
+        // 1, 2 and 3 make room for the 15 extra bytes
+        assert_eq!(*evicted.borrow(), vec![1, 2, 3]);
+        assert_eq!(cache.weight(), 95);
+        assert_eq!(cache.put(0, "x".repeat(5)).map(|val| val.len()), Some(10));
+        assert_eq!(cache.weight(), 90);
+
+        cache.resize(3);
+        assert_eq!(cache.len(), 3);
+        assert_eq!(*evicted.borrow(), vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        assert!(cache.iter().map(|(key, _)| *key).eq(vec![0, 10, 9]));
+
+        // heavier than the limit on its own
+        cache.put(11, "x".repeat(101));
+        assert!(cache.is_empty());
+        assert_eq!(cache.weight(), 0);
+    }
+
+    #[test]
+    fn lru_random() {
+        // against a vector in use order, most recent last
+        let mut cache = LruCache::new(50);
+        let mut model: Vec<(u32, u32)> = Vec::new();
+        for _ in 0..20000 {
+            let key = random::<u32>() % 100;
+            let pos = model.iter().position(|(k, _)| *k == key);
+            match random::<u32>() % 3 {
+                0 => {
+                    assert_eq!(cache.get(&key), pos.map(|pos| &model[pos].1));
+                    if let Some(pos) = pos {
+                        let entry = model.remove(pos);
+                        model.push(entry);
+                    }
+                }
+                1 => {
+                    assert_eq!(cache.pop(&key), pos.map(|pos| model.remove(pos).1));
+                }
+                _ => {
+                    let val = random();
+                    assert_eq!(cache.put(key, val), pos.map(|pos| model.remove(pos).1));
+                    model.push((key, val));
+                    if model.len() > 50 {
+                        model.remove(0);
+                    }
+                }
+            }
+            assert_eq!(cache.len(), model.len());
+        }
+        assert!(cache
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .eq(model.iter().rev().copied()));
+    }
+
+    #[test]
+    fn lfu() {
+        let evicted = Rc::new(RefCell::new(Vec::new()));
+        let log = evicted.clone();
+        let mut cache = LfuCache::new(3).on_evict(move |key, _| log.borrow_mut().push(key));
+        cache.put(1, 'a');
+        cache.put(2, 'b');
+        cache.put(3, 'c');
+        cache.get(&1);
+        cache.get(&1);
+        cache.get(&2);
+        assert_eq!(cache.uses(&1), Some(3));
+        assert_eq!(cache.peek_lfu(), Some((&3, &'c')));
+
+        // 3 is used least, and the new entry is not evicted right away
+        cache.put(4, 'd');
+        cache.put(5, 'e');
+        assert_eq!(*evicted.borrow(), vec![3, 4]);
+        cache.get(&5);
+        // 2 and 5 both have two uses, 2 was used longer ago
+        assert_eq!(cache.put(6, 'f'), None);
+        assert_eq!(*evicted.borrow(), vec![3, 4, 2]);
+        assert_eq!(cache.put(1, 'g'), Some('a'));
+        assert_eq!(cache.uses(&1), Some(4));
+        assert_eq!(cache.pop(&6), Some('f'));
+        // the next fewest uses is found after the last one-use entry is gone
+        assert_eq!(cache.peek_lfu(), Some((&5, &'e')));
+        assert_eq!(cache.pop_lfu(), Some((5, 'e')));
+        *cache.get_mut(&1).unwrap() = 'h';
+        assert_eq!(cache.peek(&1), Some(&'h'));
+
+        cache.resize(0);
+        assert!(cache.is_empty());
+        assert_eq!(cache.put(7, 'i'), None);
+        assert!(cache.is_empty());
+        assert_eq!(*evicted.borrow(), vec![3, 4, 2, 1, 7]);
+    }
+
+    #[test]
+    fn lfu_limits() {
+        let evicted = Rc::new(RefCell::new(Vec::new()));
+        let log = evicted.clone();
+        let mut cache = LfuCache::new(10)
+            .with_weigher(100, |_: &u32, val: &String| val.len())
+            .on_evict(move |key, _| log.borrow_mut().push(key));
+
+        for i in 0..10 {
+            cache.put(i, "x".repeat(10));
+        }
+        assert_eq!(cache.weight(), 100);
+        cache.get(&0);
+        cache.get(&2);
+        cache.put(10, "x".repeat(25));
+        // the one-use entries make room for the 15 extra bytes, oldest first
+        assert_eq!(*evicted.borrow(), vec![1, 3, 4]);
+        assert_eq!(cache.weight(), 95);
+        assert_eq!(cache.put(0, "x".repeat(5)).map(|val| val.len()), Some(10));
+        assert_eq!(cache.weight(), 90);
+        assert_eq!(cache.uses(&0), Some(3));
+
+        cache.resize(3);
+        assert_eq!(cache.len(), 3);
+        assert_eq!(*evicted.borrow(), vec![1, 3, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(cache.weight(), 40);
+        assert_eq!(cache.peek_lfu().map(|(key, _)| *key), Some(10));
+
+        // heavier than the limit on its own
+        cache.put(11, "x".repeat(101));
+        assert_eq!(cache.len(), 3);
+        assert_eq!(evicted.borrow().last(), Some(&11));
+
+        // a heavier value evicts the others, fewest uses first
+        cache.put(0, "x".repeat(95));
+        assert_eq!(*evicted.borrow(), vec![1, 3, 4, 5, 6, 7, 8, 9, 11, 10, 2]);
+        assert!(cache.contains(&0));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.weight(), 95);
+    }
+}
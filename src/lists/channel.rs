@@ -0,0 +1,580 @@
+use super::queue::Queue;
+use futures::{Sink, Stream};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+// the other side of the channel is gone, or it was closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Closed;
+
+// the channel is closed, the element comes back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    Full(T),
+    Closed(T),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    Empty,
+    Closed,
+}
+
+// Tasks waiting on one side of the channel, woken in the order they came.
+// A task that is woken but goes away without polling again passes the
+// wakeup on, so it is never lost.
+struct Waiters {
+    tasks: Vec<(u64, Waker)>,
+    next_id: u64,
+}
+
+impl Waiters {
+    fn new() -> Self {
+        Self {
+            tasks: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    fn register(&mut self, id: &mut Option<u64>, waker: &Waker) {
+        if let Some(id) = *id {
+            if let Some((_, old)) = self.tasks.iter_mut().find(|(task, _)| *task == id) {
+                if !old.will_wake(waker) {
+                    *old = waker.clone();
+                }
+                return;
+            }
+        }
+        *id = Some(self.next_id);
+        self.tasks.push((self.next_id, waker.clone()));
+        self.next_id += 1;
+    }
+
+    // false if the task was already woken
+    fn remove(&mut self, id: u64) -> bool {
+        match self.tasks.iter().position(|(task, _)| *task == id) {
+            Some(i) => {
+                self.tasks.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // the task stopped waiting, without taking what it was woken for
+    fn cancel(&mut self, id: &mut Option<u64>) {
+        if let Some(id) = id.take() {
+            if !self.remove(id) {
+                self.wake_one();
+            }
+        }
+    }
+
+    fn wake_one(&mut self) {
+        if !self.tasks.is_empty() {
+            self.tasks.remove(0).1.wake();
+        }
+    }
+
+    fn wake_all(&mut self) {
+        for (_, waker) in self.tasks.drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+struct State<T> {
+    queue: Queue<T>,
+    len: usize,
+    // slots taken by Sink::poll_ready, filled by start_send
+    reserved: usize,
+    cap: Option<usize>,
+    senders: usize,
+    receivers: usize,
+    closed: bool,
+    send_waiters: Waiters,
+    recv_waiters: Waiters,
+}
+
+impl<T> State<T> {
+    fn is_full(&self) -> bool {
+        self.cap.is_some_and(|cap| self.len + self.reserved >= cap)
+    }
+
+    fn push(&mut self, elem: T) {
+        self.queue.enqueue(elem);
+        self.len += 1;
+        self.recv_waiters.wake_one();
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        let elem = self.queue.dequeue()?;
+        self.len -= 1;
+        self.send_waiters.wake_one();
+        Some(elem)
+    }
+
+    fn close(&mut self) -> bool {
+        if self.closed {
+            return false;
+        }
+        self.closed = true;
+        self.send_waiters.wake_all();
+        self.recv_waiters.wake_all();
+        true
+    }
+}
+
+// Sending and receiving ends of a channel. Both can be cloned, every
+// element goes to exactly one receiver. The channel closes when all
+// senders or all receivers are dropped, or when either side closes it,
+// receivers still get the elements sent before that.
+pub struct Sender<T> {
+    shared: Arc<Mutex<State<T>>>,
+    // used by the Sink impl
+    reserved: bool,
+    waiter: Option<u64>,
+}
+
+pub struct Receiver<T> {
+    shared: Arc<Mutex<State<T>>>,
+    // used by the Stream impl
+    waiter: Option<u64>,
+}
+
+pub struct SendFuture<'a, T> {
+    sender: &'a Sender<T>,
+    elem: Option<T>,
+    waiter: Option<u64>,
+}
+
+pub struct RecvFuture<'a, T> {
+    receiver: &'a Receiver<T>,
+    waiter: Option<u64>,
+}
+
+// a channel holding at most cap elements, send waits while it is full
+pub fn bounded<T>(cap: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(cap > 0, "capacity must be positive");
+    channel(Some(cap))
+}
+
+pub fn unbounded<T>() -> (Sender<T>, Receiver<T>) {
+    channel(None)
+}
+
+fn channel<T>(cap: Option<usize>) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Mutex::new(State {
+        queue: Queue::new(),
+        len: 0,
+        reserved: 0,
+        cap,
+        senders: 1,
+        receivers: 1,
+        closed: false,
+        send_waiters: Waiters::new(),
+        recv_waiters: Waiters::new(),
+    }));
+    let sender = Sender {
+        shared: shared.clone(),
+        reserved: false,
+        waiter: None,
+    };
+    (
+        sender,
+        Receiver {
+            shared,
+            waiter: None,
+        },
+    )
+}
+
+fn lock<T>(shared: &Mutex<State<T>>) -> MutexGuard<'_, State<T>> {
+    shared.lock().unwrap()
+}
+
+impl<T> Sender<T> {
+    pub fn send(&self, elem: T) -> SendFuture<'_, T> {
+        SendFuture {
+            sender: self,
+            elem: Some(elem),
+            waiter: None,
+        }
+    }
+
+    pub fn try_send(&self, elem: T) -> Result<(), TrySendError<T>> {
+        let mut state = lock(&self.shared);
+        if state.closed {
+            Err(TrySendError::Closed(elem))
+        } else if state.is_full() {
+            Err(TrySendError::Full(elem))
+        } else {
+            state.push(elem);
+            Ok(())
+        }
+    }
+
+    fn poll_send(
+        &self,
+        cx: &mut Context<'_>,
+        elem: &mut Option<T>,
+        waiter: &mut Option<u64>,
+    ) -> Poll<Result<(), SendError<T>>> {
+        let mut state = lock(&self.shared);
+        if state.closed {
+            state.send_waiters.cancel(waiter);
+            return Poll::Ready(Err(SendError(elem.take().unwrap())));
+        }
+        if state.is_full() {
+            state.send_waiters.register(waiter, cx.waker());
+            return Poll::Pending;
+        }
+        if let Some(id) = waiter.take() {
+            state.send_waiters.remove(id);
+        }
+        state.push(elem.take().unwrap());
+        Poll::Ready(Ok(()))
+    }
+
+    // true if this call closed the channel
+    pub fn close(&self) -> bool {
+        lock(&self.shared).close()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        lock(&self.shared).closed
+    }
+
+    pub fn len(&self) -> usize {
+        lock(&self.shared).len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> Option<usize> {
+        lock(&self.shared).cap
+    }
+}
+
+impl<T> Receiver<T> {
+    pub fn recv(&self) -> RecvFuture<'_, T> {
+        RecvFuture {
+            receiver: self,
+            waiter: None,
+        }
+    }
+
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut state = lock(&self.shared);
+        match state.pop() {
+            Some(elem) => Ok(elem),
+            None if state.closed => Err(TryRecvError::Closed),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    fn poll_recv(&self, cx: &mut Context<'_>, waiter: &mut Option<u64>) -> Poll<Result<T, Closed>> {
+        let mut state = lock(&self.shared);
+        if let Some(elem) = state.pop() {
+            if let Some(id) = waiter.take() {
+                state.recv_waiters.remove(id);
+            }
+            return Poll::Ready(Ok(elem));
+        }
+        if state.closed {
+            state.recv_waiters.cancel(waiter);
+            return Poll::Ready(Err(Closed));
+        }
+        state.recv_waiters.register(waiter, cx.waker());
+        Poll::Pending
+    }
+
+    pub fn close(&self) -> bool {
+        lock(&self.shared).close()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        lock(&self.shared).closed
+    }
+
+    pub fn len(&self) -> usize {
+        lock(&self.shared).len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> Option<usize> {
+        lock(&self.shared).cap
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        lock(&self.shared).senders += 1;
+        Self {
+            shared: self.shared.clone(),
+            reserved: false,
+            waiter: None,
+        }
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        lock(&self.shared).receivers += 1;
+        Self {
+            shared: self.shared.clone(),
+            waiter: None,
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = lock(&self.shared);
+        if self.reserved {
+            state.reserved -= 1;
+            state.send_waiters.wake_one();
+        }
+        state.send_waiters.cancel(&mut self.waiter);
+        state.senders -= 1;
+        if state.senders == 0 {
+            state.close();
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut state = lock(&self.shared);
+        state.recv_waiters.cancel(&mut self.waiter);
+        state.receivers -= 1;
+        if state.receivers == 0 {
+            state.close();
+        }
+    }
+}
+
+impl<T> Future for SendFuture<'_, T> {
+    type Output = Result<(), SendError<T>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        this.sender.poll_send(cx, &mut this.elem, &mut this.waiter)
+    }
+}
+
+impl<T> Future for RecvFuture<'_, T> {
+    type Output = Result<T, Closed>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        this.receiver.poll_recv(cx, &mut this.waiter)
+    }
+}
+
+impl<T> Drop for SendFuture<'_, T> {
+    fn drop(&mut self) {
+        if self.waiter.is_some() {
+            lock(&self.sender.shared)
+                .send_waiters
+                .cancel(&mut self.waiter);
+        }
+    }
+}
+
+impl<T> Drop for RecvFuture<'_, T> {
+    fn drop(&mut self) {
+        if self.waiter.is_some() {
+            lock(&self.receiver.shared)
+                .recv_waiters
+                .cancel(&mut self.waiter);
+        }
+    }
+}
+
+// elem is never pinned, so the future is Unpin whatever T is
+impl<T> Unpin for SendFuture<'_, T> {}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let this = self.get_mut();
+        let mut waiter = this.waiter.take();
+        let poll = this.poll_recv(cx, &mut waiter);
+        this.waiter = waiter;
+        poll.map(Result::ok)
+    }
+}
+
+impl<T> Sink<T> for Sender<T> {
+    type Error = Closed;
+
+    // reserve a slot, so start_send can't find the channel full
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Closed>> {
+        let this = self.get_mut();
+        if this.reserved {
+            return Poll::Ready(Ok(()));
+        }
+        let mut state = lock(&this.shared);
+        if state.closed {
+            state.send_waiters.cancel(&mut this.waiter);
+            return Poll::Ready(Err(Closed));
+        }
+        if state.is_full() {
+            state.send_waiters.register(&mut this.waiter, cx.waker());
+            return Poll::Pending;
+        }
+        if let Some(id) = this.waiter.take() {
+            state.send_waiters.remove(id);
+        }
+        state.reserved += 1;
+        this.reserved = true;
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, elem: T) -> Result<(), Closed> {
+        let this = self.get_mut();
+        assert!(this.reserved, "start_send without poll_ready");
+        let mut state = lock(&this.shared);
+        state.reserved -= 1;
+        this.reserved = false;
+        if state.closed {
+            return Err(Closed);
+        }
+        state.push(elem);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Closed>> {
+        Poll::Ready(Ok(()))
+    }
+
+    // closes the channel for every sender, the receivers still get what is
+    // queued
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Closed>> {
+        let this = self.get_mut();
+        let mut state = lock(&this.shared);
+        if this.reserved {
+            state.reserved -= 1;
+            this.reserved = false;
+        }
+        state.send_waiters.cancel(&mut this.waiter);
+        state.close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{bounded, unbounded, Closed, SendError, TryRecvError, TrySendError};
+    use futures::executor::block_on;
+    use futures::{SinkExt, StreamExt};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn basic() {
+        let (send, recv) = bounded(2);
+        assert_eq!(send.capacity(), Some(2));
+        assert_eq!(recv.try_recv(), Err(TryRecvError::Empty));
+        send.try_send(1).unwrap();
+        block_on(send.send(2)).unwrap();
+        assert_eq!(send.try_send(3), Err(TrySendError::Full(3)));
+        assert_eq!(recv.len(), 2);
+        assert_eq!(block_on(recv.recv()), Ok(1));
+        send.try_send(3).unwrap();
+        assert_eq!(recv.try_recv(), Ok(2));
+
+        // receivers drain the channel after the senders are gone
+        let other = send.clone();
+        drop(send);
+        assert!(!recv.is_closed());
+        drop(other);
+        assert!(recv.is_closed());
+        assert_eq!(block_on(recv.recv()), Ok(3));
+        assert_eq!(block_on(recv.recv()), Err(Closed));
+        assert_eq!(recv.try_recv(), Err(TryRecvError::Closed));
+
+        let (send, recv) = unbounded();
+        assert_eq!(send.capacity(), None);
+        for i in 0..100 {
+            send.try_send(i).unwrap();
+        }
+        assert!(recv.close());
+        assert!(!send.close());
+        assert_eq!(block_on(send.send(100)), Err(SendError(100)));
+        assert_eq!(
+            block_on(recv.take(200).collect::<Vec<_>>()),
+            (0..100).collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn mpmc() {
+        const TASKS: usize = 4;
+        const ELEMS: usize = 10000;
+
+        let (send, recv) = bounded(16);
+        let sum = Arc::new(AtomicUsize::new(0));
+        let mut handles = Vec::new();
+        for _ in 0..TASKS {
+            let send = send.clone();
+            handles.push(tokio::spawn(async move {
+                for i in 0..ELEMS {
+                    send.send(i).await.unwrap();
+                }
+            }));
+        }
+        drop(send);
+        for _ in 0..TASKS {
+            let (recv, sum) = (recv.clone(), sum.clone());
+            handles.push(tokio::spawn(async move {
+                while let Ok(i) = recv.recv().await {
+                    assert!(recv.len() <= 16);
+                    sum.fetch_add(i, Ordering::Relaxed);
+                }
+            }));
+        }
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(sum.load(Ordering::Relaxed), TASKS * ELEMS * (ELEMS - 1) / 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn stream_sink() {
+        let (mut send, recv) = bounded(4);
+        let task = tokio::spawn(async move {
+            send.send_all(&mut futures::stream::iter((0..1000).map(Ok)))
+                .await
+                .unwrap();
+        });
+        let elems = recv.collect::<Vec<_>>().await;
+        task.await.unwrap();
+        assert_eq!(elems, (0..1000).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn sink_close() {
+        let (mut send, mut recv) = bounded(4);
+        let other = send.clone();
+        send.feed(1).await.unwrap();
+        // the inherent close and send shadow the Sink ones
+        SinkExt::close(&mut send).await.unwrap();
+        assert!(other.is_closed());
+        assert_eq!(SinkExt::send(&mut send, 2).await, Err(Closed));
+        assert_eq!(recv.next().await, Some(1));
+        assert_eq!(recv.next().await, None);
+    }
+}
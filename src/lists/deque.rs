@@ -1,9 +1,12 @@
-use std::cell::{Ref, RefCell};
+use std::cell::{Ref, RefCell, RefMut};
+use std::marker::PhantomData;
+use std::mem;
 use std::rc::Rc;
 
 struct List<T> {
     head: Link<T>,
     tail: Link<T>,
+    len: usize,
 }
 
 type Link<T> = Option<Rc<RefCell<Node<T>>>>;
@@ -14,20 +17,39 @@ struct Node<T> {
     next: Link<T>,
 }
 
+fn new_node<T>(elem: T) -> Rc<RefCell<Node<T>>> {
+    Rc::new(RefCell::new(Node {
+        elem,
+        prev: None,
+        next: None,
+    }))
+}
+
+fn link<T>(prev: &Rc<RefCell<Node<T>>>, next: &Rc<RefCell<Node<T>>>) {
+    prev.borrow_mut().next = Some(next.clone());
+    next.borrow_mut().prev = Some(prev.clone());
+}
+
 impl<T> List<T> {
     fn new() -> List<T> {
         List {
             head: None,
             tail: None,
+            len: 0,
         }
     }
 
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
     fn push_front(&mut self, elem: T) {
-        let node = Rc::new(RefCell::new(Node {
-            elem,
-            prev: None,
-            next: None,
-        }));
+        self.len += 1;
+        let node = new_node(elem);
         match self.head.take() {
             Some(head) => {
                 head.borrow_mut().prev = Some(node.clone());
@@ -42,11 +64,8 @@ impl<T> List<T> {
     }
 
     fn push_back(&mut self, elem: T) {
-        let node = Rc::new(RefCell::new(Node {
-            elem,
-            prev: None,
-            next: None,
-        }));
+        self.len += 1;
+        let node = new_node(elem);
         match self.tail.take() {
             Some(tail) => {
                 tail.borrow_mut().next = Some(node.clone());
@@ -62,6 +81,7 @@ impl<T> List<T> {
 
     fn pop_front(&mut self) -> Option<T> {
         self.head.take().map(|head| {
+            self.len -= 1;
             match head.borrow_mut().next.take() {
                 Some(next) => {
                     self.head = Some(next.clone());
@@ -79,6 +99,7 @@ impl<T> List<T> {
 
     fn pop_back(&mut self) -> Option<T> {
         self.tail.take().map(|tail| {
+            self.len -= 1;
             match tail.borrow_mut().prev.take() {
                 Some(prev) => {
                     self.tail = Some(prev.clone());
@@ -105,6 +126,405 @@ impl<T> List<T> {
             .as_ref()
             .map(|node| Ref::map(node.borrow(), |node| &node.elem))
     }
+
+    fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            front: self.head.clone(),
+            back: self.tail.clone(),
+            len: self.len,
+            _list: PhantomData,
+        }
+    }
+
+    // the cursor starts on the first element, or the ghost if it is empty
+    fn cursor_front_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            cur: self.head.clone(),
+            peeked: None,
+            index: 0,
+            list: self,
+        }
+    }
+
+    fn cursor_back_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            cur: self.tail.clone(),
+            peeked: None,
+            index: self.len.saturating_sub(1),
+            list: self,
+        }
+    }
+
+    // take all nodes out, leaving the list empty
+    fn take(&mut self) -> (Link<T>, Link<T>, usize) {
+        (
+            self.head.take(),
+            self.tail.take(),
+            mem::replace(&mut self.len, 0),
+        )
+    }
+}
+
+impl<T> Default for List<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// A &T can't outlive the Ref guard of its node, so the iterator walks the Rc
+// links and hands out the nodes as Elems, which give out the guards.
+struct Iter<'a, T> {
+    front: Link<T>,
+    back: Link<T>,
+    // the ends only meet in the middle, stop by counting
+    len: usize,
+    _list: PhantomData<&'a List<T>>,
+}
+
+// an element reached by Iter
+struct Elem<'a, T> {
+    node: Rc<RefCell<Node<T>>>,
+    _list: PhantomData<&'a List<T>>,
+}
+
+impl<T> Elem<'_, T> {
+    fn borrow(&self) -> Ref<'_, T> {
+        Ref::map(self.node.borrow(), |node| &node.elem)
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = Elem<'a, T>;
+
+    fn next(&mut self) -> Option<Elem<'a, T>> {
+        if self.len == 0 {
+            return None;
+        }
+        let node = self.front.take()?;
+        self.front = node.borrow().next.clone();
+        self.len -= 1;
+        Some(Elem {
+            node,
+            _list: PhantomData,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<Elem<'a, T>> {
+        if self.len == 0 {
+            return None;
+        }
+        let node = self.back.take()?;
+        self.back = node.borrow().prev.clone();
+        self.len -= 1;
+        Some(Elem {
+            node,
+            _list: PhantomData,
+        })
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<'a, T> IntoIterator for &'a List<T> {
+    type Item = Elem<'a, T>;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+// Iter and Elem hold nodes like the cursor does, see its Drop impl
+impl<T> Drop for Iter<'_, T> {
+    fn drop(&mut self) {}
+}
+
+impl<T> Drop for Elem<'_, T> {
+    fn drop(&mut self) {}
+}
+
+struct IntoIter<T>(List<T>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.0.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.0.pop_back()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> IntoIterator for List<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self)
+    }
+}
+
+// A cursor points at an element, or at the "ghost" between the back and the
+// front of the list. Moving past either end lands on the ghost, moving again
+// wraps around.
+struct CursorMut<'a, T> {
+    list: &'a mut List<T>,
+    // strong, so current can hand out a guard, see the Drop impl
+    cur: Link<T>,
+    // the node peek_next or peek_prev handed out a guard to, let go by the
+    // next call that moves the cursor or changes the list
+    peeked: Link<T>,
+    // index of cur, the ghost is at list.len
+    index: usize,
+}
+
+impl<'a, T> CursorMut<'a, T> {
+    fn index(&self) -> Option<usize> {
+        self.cur.as_ref().map(|_| self.index)
+    }
+
+    fn cur(&mut self) -> Link<T> {
+        self.peeked = None;
+        self.cur.clone()
+    }
+
+    fn move_next(&mut self) {
+        let cur = match self.cur() {
+            None => {
+                self.index = 0;
+                self.list.head.clone()
+            }
+            Some(cur) => {
+                self.index += 1;
+                cur.borrow().next.clone()
+            }
+        };
+        self.cur = cur;
+    }
+
+    fn move_prev(&mut self) {
+        let cur = match self.cur() {
+            None => {
+                self.index = self.list.len.saturating_sub(1);
+                self.list.tail.clone()
+            }
+            Some(cur) => {
+                let prev = cur.borrow().prev.clone();
+                self.index = if prev.is_some() {
+                    self.index - 1
+                } else {
+                    self.list.len
+                };
+                prev
+            }
+        };
+        self.cur = cur;
+    }
+
+    fn current(&mut self) -> Option<RefMut<'_, T>> {
+        self.cur
+            .as_ref()
+            .map(|cur| RefMut::map(cur.borrow_mut(), |cur| &mut cur.elem))
+    }
+
+    fn peek_next(&mut self) -> Option<RefMut<'_, T>> {
+        self.peeked = match &self.cur {
+            None => self.list.head.clone(),
+            Some(cur) => cur.borrow().next.clone(),
+        };
+        self.peeked
+            .as_ref()
+            .map(|next| RefMut::map(next.borrow_mut(), |next| &mut next.elem))
+    }
+
+    fn peek_prev(&mut self) -> Option<RefMut<'_, T>> {
+        self.peeked = match &self.cur {
+            None => self.list.tail.clone(),
+            Some(cur) => cur.borrow().prev.clone(),
+        };
+        self.peeked
+            .as_ref()
+            .map(|prev| RefMut::map(prev.borrow_mut(), |prev| &mut prev.elem))
+    }
+
+    // at the ghost this inserts at the front
+    fn insert_after(&mut self, elem: T) {
+        match self.cur() {
+            None => {
+                self.list.push_front(elem);
+                self.index += 1;
+            }
+            Some(cur) => {
+                let node = new_node(elem);
+                let next = cur.borrow_mut().next.take();
+                match next {
+                    Some(next) => link(&node, &next),
+                    None => self.list.tail = Some(node.clone()),
+                }
+                link(&cur, &node);
+                self.list.len += 1;
+            }
+        }
+    }
+
+    // at the ghost this inserts at the back
+    fn insert_before(&mut self, elem: T) {
+        match self.cur() {
+            None => self.list.push_back(elem),
+            Some(cur) => {
+                let node = new_node(elem);
+                let prev = cur.borrow_mut().prev.take();
+                match prev {
+                    Some(prev) => link(&prev, &node),
+                    None => self.list.head = Some(node.clone()),
+                }
+                link(&node, &cur);
+                self.list.len += 1;
+            }
+        }
+        self.index += 1;
+    }
+
+    // remove the current element and move to the next one
+    fn remove_current(&mut self) -> Option<T> {
+        let cur = self.cur()?;
+        let prev = cur.borrow_mut().prev.take();
+        let next = cur.borrow_mut().next.take();
+        match &prev {
+            Some(prev) => prev.borrow_mut().next = next.clone(),
+            None => self.list.head = next.clone(),
+        }
+        match &next {
+            Some(next) => next.borrow_mut().prev = prev,
+            None => self.list.tail = prev,
+        }
+        self.cur = next;
+        self.list.len -= 1;
+        Some(Rc::try_unwrap(cur).ok().unwrap().into_inner().elem)
+    }
+
+    // everything after the cursor, all of the list at the ghost
+    fn split_after(&mut self) -> List<T> {
+        let cur = match self.cur() {
+            None => {
+                let (head, tail, len) = self.list.take();
+                self.index = 0;
+                return List { head, tail, len };
+            }
+            Some(cur) => cur,
+        };
+        let next = cur.borrow_mut().next.take();
+        match next {
+            None => List::new(),
+            Some(next) => {
+                next.borrow_mut().prev = None;
+                let len = self.list.len - self.index - 1;
+                self.list.len = self.index + 1;
+                let tail = self.list.tail.replace(cur.clone());
+                List {
+                    head: Some(next),
+                    tail,
+                    len,
+                }
+            }
+        }
+    }
+
+    // everything before the cursor, all of the list at the ghost
+    fn split_before(&mut self) -> List<T> {
+        let cur = match self.cur() {
+            None => {
+                let (head, tail, len) = self.list.take();
+                self.index = 0;
+                return List { head, tail, len };
+            }
+            Some(cur) => cur,
+        };
+        let prev = cur.borrow_mut().prev.take();
+        match prev {
+            None => List::new(),
+            Some(prev) => {
+                prev.borrow_mut().next = None;
+                let len = mem::replace(&mut self.index, 0);
+                self.list.len -= len;
+                let head = self.list.head.replace(cur.clone());
+                List {
+                    head,
+                    tail: Some(prev),
+                    len,
+                }
+            }
+        }
+    }
+
+    // move the elements of other after the cursor, at the ghost to the front
+    fn splice_after(&mut self, mut other: List<T>) {
+        let (head, tail, len) = match other.take() {
+            (Some(head), Some(tail), len) => (head, tail, len),
+            _ => return,
+        };
+        let next = match self.cur() {
+            None => {
+                self.index += len;
+                self.list.head.replace(head)
+            }
+            Some(cur) => {
+                let next = cur.borrow_mut().next.take();
+                link(&cur, &head);
+                next
+            }
+        };
+        match next {
+            Some(next) => link(&tail, &next),
+            None => self.list.tail = Some(tail),
+        }
+        self.list.len += len;
+    }
+
+    // move the elements of other before the cursor, at the ghost to the back
+    fn splice_before(&mut self, mut other: List<T>) {
+        let (head, tail, len) = match other.take() {
+            (Some(head), Some(tail), len) => (head, tail, len),
+            _ => return,
+        };
+        let prev = match self.cur() {
+            None => self.list.tail.replace(tail),
+            Some(cur) => {
+                let prev = cur.borrow_mut().prev.take();
+                link(&tail, &cur);
+                prev
+            }
+        };
+        match prev {
+            Some(prev) => link(&prev, &head),
+            None => self.list.head = Some(head),
+        }
+        self.index += len;
+        self.list.len += len;
+    }
+}
+
+// Nothing to do, but with a Drop impl the list stays borrowed until the
+// cursor is dropped, so a cursor that is out of use can't keep its node
+// alive and break Rc::try_unwrap in pop.
+impl<T> Drop for CursorMut<'_, T> {
+    fn drop(&mut self) {}
 }
 
 impl<T> Drop for List<T> {
@@ -131,5 +551,136 @@ mod test {
         list.push_front(2);
         assert_eq!(*list.peek_front().unwrap(), 2);
         assert_eq!(*list.peek_back().unwrap(), 1);
+        assert_eq!(list.len(), 2);
+    }
+
+    fn from_slice(vals: &[i32]) -> List<i32> {
+        let mut list = List::new();
+        for val in vals {
+            list.push_back(*val);
+        }
+        list
+    }
+
+    fn to_vec(list: &List<i32>) -> Vec<i32> {
+        let vals: Vec<_> = list.iter().map(|elem| *elem.borrow()).collect();
+        // the back links must agree with the front links
+        let mut rev: Vec<_> = list.iter().rev().map(|elem| *elem.borrow()).collect();
+        rev.reverse();
+        assert_eq!(vals, rev);
+        assert_eq!(vals.len(), list.len());
+        vals
+    }
+
+    #[test]
+    fn iter() {
+        let list = from_slice(&[1, 2, 3, 4, 5]);
+        let rev: Vec<_> = list.iter().rev().map(|elem| *elem.borrow()).collect();
+        assert_eq!(rev, vec![5, 4, 3, 2, 1]);
+
+        let mut iter = list.iter();
+        assert_eq!(iter.len(), 5);
+        assert_eq!(*iter.next().unwrap().borrow(), 1);
+        assert_eq!(*iter.next_back().unwrap().borrow(), 5);
+        assert_eq!(*iter.next().unwrap().borrow(), 2);
+        assert_eq!(*iter.next_back().unwrap().borrow(), 4);
+        assert_eq!(iter.len(), 1);
+        assert_eq!(*iter.next().unwrap().borrow(), 3);
+        assert!(iter.next().is_none());
+        assert!(iter.next_back().is_none());
+        drop(iter);
+
+        let mut iter = list.into_iter();
+        assert_eq!(iter.len(), 5);
+        assert_eq!(iter.next(), Some(1));
+        assert_eq!(iter.next_back(), Some(5));
+        assert_eq!(iter.collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert!(List::<i32>::new().iter().next().is_none());
+    }
+
+    #[test]
+    fn cursor() {
+        let mut list = from_slice(&[1, 2, 3]);
+        let mut cursor = list.cursor_front_mut();
+        assert_eq!(cursor.index(), Some(0));
+        assert!(cursor.peek_prev().is_none());
+        *cursor.current().unwrap() = 10;
+        cursor.move_next();
+        assert_eq!(cursor.peek_next().as_deref(), Some(&3));
+        cursor.insert_before(15);
+        cursor.insert_after(25);
+        assert_eq!(cursor.index(), Some(2));
+        assert_eq!(cursor.remove_current(), Some(2));
+        assert_eq!(cursor.current().as_deref(), Some(&25));
+        cursor.move_next();
+        cursor.move_next();
+        // the ghost, then around to the front
+        assert_eq!(cursor.index(), None);
+        assert!(cursor.current().is_none());
+        assert_eq!(cursor.peek_next().as_deref(), Some(&10));
+        *cursor.peek_prev().unwrap() = 30;
+        cursor.insert_before(4);
+        cursor.insert_after(0);
+        cursor.move_next();
+        assert_eq!(cursor.index(), Some(0));
+        cursor.move_prev();
+        cursor.move_prev();
+        assert_eq!(cursor.current().as_deref(), Some(&4));
+        assert_eq!(cursor.index(), Some(5));
+        drop(cursor);
+        assert_eq!(to_vec(&list), vec![0, 10, 15, 25, 30, 4]);
+
+        let mut cursor = list.cursor_back_mut();
+        assert_eq!(cursor.remove_current(), Some(4));
+        assert_eq!(cursor.index(), None);
+        drop(cursor);
+        assert_eq!(to_vec(&list), vec![0, 10, 15, 25, 30]);
+
+        let mut cursor = list.cursor_front_mut();
+        assert_eq!(cursor.peek_next().as_deref(), Some(&10));
+        cursor.move_next();
+        // the node peeked at is let go before it is removed
+        assert_eq!(cursor.remove_current(), Some(10));
+        drop(cursor);
+        assert_eq!(to_vec(&list), vec![0, 15, 25, 30]);
+    }
+
+    #[test]
+    fn split_splice() {
+        let mut list = from_slice(&[1, 2, 3, 4, 5]);
+        let mut cursor = list.cursor_front_mut();
+        cursor.move_next();
+        cursor.move_next();
+        let after = cursor.split_after();
+        assert_eq!(cursor.index(), Some(2));
+        let before = cursor.split_before();
+        assert_eq!(cursor.index(), Some(0));
+        assert_eq!(to_vec(&before), vec![1, 2]);
+        assert_eq!(to_vec(&after), vec![4, 5]);
+
+        cursor.splice_after(after);
+        cursor.splice_before(before);
+        assert_eq!(cursor.index(), Some(2));
+        assert_eq!(cursor.current().as_deref(), Some(&3));
+        cursor.splice_after(List::new());
+        cursor.move_prev();
+        cursor.move_prev();
+        cursor.move_prev();
+        // splicing at the ghost goes to the ends
+        cursor.splice_after(from_slice(&[-1, 0]));
+        cursor.splice_before(from_slice(&[6]));
+        assert_eq!(cursor.index(), None);
+        cursor.move_next();
+        assert_eq!(cursor.current().as_deref(), Some(&-1));
+        drop(cursor);
+        assert_eq!(to_vec(&list), vec![-1, 0, 1, 2, 3, 4, 5, 6]);
+
+        let mut cursor = list.cursor_back_mut();
+        cursor.move_next();
+        let all = cursor.split_before();
+        drop(cursor);
+        assert_eq!(to_vec(&all), vec![-1, 0, 1, 2, 3, 4, 5, 6]);
+        assert!(list.is_empty());
+        assert!(list.peek_front().is_none());
     }
 }
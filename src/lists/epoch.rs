@@ -0,0 +1,95 @@
+use super::reclaim::{Record, Registry, Retired};
+use std::sync::atomic::{fence, AtomicUsize, Ordering};
+
+// retired nodes a participant collects before it tries to free some
+const COLLECT_AT: usize = 64;
+
+// epoch << 1 | 1 while pinned, 0 otherwise
+type Participant = Record<AtomicUsize>;
+
+// Epoch based reclamation (Fraser, 2004). Threads pin the current epoch
+// while they read, and the epoch only moves on once every pinned thread
+// has seen it. A node retired in epoch e is freed once the epoch reaches
+// e + 2, no guard from before its removal is left by then.
+pub(super) struct Collector {
+    epoch: AtomicUsize,
+    participants: Registry<AtomicUsize>,
+}
+
+pub(super) struct Guard<'a> {
+    collector: &'a Collector,
+    participant: &'a Participant,
+}
+
+impl Collector {
+    pub(super) fn new() -> Self {
+        Self {
+            epoch: AtomicUsize::new(0),
+            participants: Registry::new(),
+        }
+    }
+
+    pub(super) fn pin(&self) -> Guard<'_> {
+        let participant = self.participants.acquire(|| AtomicUsize::new(0));
+        let epoch = self.epoch.load(Ordering::Relaxed);
+        participant.data.store(epoch << 1 | 1, Ordering::Relaxed);
+        fence(Ordering::SeqCst);
+        Guard {
+            collector: self,
+            participant,
+        }
+    }
+
+    // move the epoch on if every pinned thread is in it, returns the epoch
+    fn try_advance(&self) -> usize {
+        let epoch = self.epoch.load(Ordering::Relaxed);
+        fence(Ordering::SeqCst);
+        for participant in self.participants.iter() {
+            let local = participant.data.load(Ordering::Relaxed);
+            if local & 1 == 1 && local >> 1 != epoch {
+                return epoch;
+            }
+        }
+        fence(Ordering::Acquire);
+        match self
+            .epoch
+            .compare_exchange(epoch, epoch + 1, Ordering::Release, Ordering::Relaxed)
+        {
+            Ok(_) => epoch + 1,
+            Err(now) => now,
+        }
+    }
+}
+
+impl Guard<'_> {
+    /// Free the Box behind ptr once no guard from before now is left.
+    ///
+    /// # Safety
+    ///
+    /// ptr came from Box::into_raw, is unreachable for new readers, and is
+    /// retired only once.
+    pub(super) unsafe fn retire<T>(&self, ptr: *mut T) {
+        let retired = self.participant.retired();
+        retired.push(Retired::new(
+            ptr,
+            self.collector.epoch.load(Ordering::SeqCst),
+        ));
+        if retired.len() >= COLLECT_AT {
+            let epoch = self.collector.try_advance();
+            retired.retain(|retired| {
+                if retired.epoch + 2 > epoch {
+                    return true;
+                }
+                retired.free();
+                false
+            });
+        }
+    }
+}
+
+impl Drop for Guard<'_> {
+    fn drop(&mut self) {
+        self.participant.data.store(0, Ordering::Release);
+        self.participant.release();
+    }
+}
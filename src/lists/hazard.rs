@@ -0,0 +1,106 @@
+use super::reclaim::{Record, Registry, Retired};
+use std::ptr;
+use std::sync::atomic::{fence, AtomicPtr, Ordering};
+
+// hazard pointers per guard, enough for the structures in this module
+pub(super) const SLOTS: usize = 2;
+// retired nodes a guard collects before it scans the hazards
+const SCAN_AT: usize = 64;
+
+// Hazard pointer reclamation (Michael, 2004). A thread publishes the nodes it
+// is about to read, and a removed node is only freed once no hazard pointer
+// points at it.
+pub(super) struct Domain {
+    records: Registry<[AtomicPtr<()>; SLOTS]>,
+}
+
+pub(super) struct Guard<'a> {
+    domain: &'a Domain,
+    record: &'a Record<[AtomicPtr<()>; SLOTS]>,
+}
+
+impl Domain {
+    pub(super) fn new() -> Self {
+        Self {
+            records: Registry::new(),
+        }
+    }
+
+    pub(super) fn guard(&self) -> Guard<'_> {
+        let record = self
+            .records
+            .acquire(|| [(); SLOTS].map(|_| AtomicPtr::new(ptr::null_mut())));
+        Guard {
+            domain: self,
+            record,
+        }
+    }
+
+    fn hazards(&self) -> Vec<*mut ()> {
+        // order the unlink of the retired nodes before the scan, so a thread
+        // that published a hazard before it re-read the head is seen here
+        fence(Ordering::SeqCst);
+        let mut hazards = Vec::new();
+        for record in self.records.iter() {
+            for hazard in &record.data {
+                let ptr = hazard.load(Ordering::SeqCst);
+                if !ptr.is_null() {
+                    hazards.push(ptr);
+                }
+            }
+        }
+        hazards.sort_unstable();
+        hazards
+    }
+}
+
+impl<'a> Guard<'a> {
+    // Publish ptr in the slot. The caller must check afterwards that ptr is
+    // still reachable, only then it won't be freed until the slot changes.
+    pub(super) fn protect<T>(&self, slot: usize, ptr: *mut T) {
+        self.record.data[slot].store(ptr as *mut (), Ordering::SeqCst);
+    }
+
+    // load src and protect the result, retrying until it is stable
+    pub(super) fn load<T>(&self, slot: usize, src: &AtomicPtr<T>) -> *mut T {
+        let mut ptr = src.load(Ordering::Acquire);
+        loop {
+            self.protect(slot, ptr);
+            let again = src.load(Ordering::SeqCst);
+            if again == ptr {
+                return ptr;
+            }
+            ptr = again;
+        }
+    }
+
+    /// Free the Box behind ptr once no hazard points at it.
+    ///
+    /// # Safety
+    ///
+    /// ptr came from Box::into_raw, is unreachable for new readers, and is
+    /// retired only once.
+    pub(super) unsafe fn retire<T>(&self, ptr: *mut T) {
+        let retired = self.record.retired();
+        retired.push(Retired::new(ptr, 0));
+        if retired.len() >= SCAN_AT {
+            let hazards = self.domain.hazards();
+            retired.retain(|retired| {
+                if hazards.binary_search(&retired.ptr).is_ok() {
+                    return true;
+                }
+                retired.free();
+                false
+            });
+        }
+    }
+}
+
+impl Drop for Guard<'_> {
+    fn drop(&mut self) {
+        for hazard in &self.record.data {
+            hazard.store(ptr::null_mut(), Ordering::Release);
+        }
+        self.record.release();
+    }
+}
@@ -0,0 +1,833 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+pub struct LinkedList<T> {
+    front: Link<T>,
+    back: Link<T>,
+    len: usize,
+    // we own the nodes and their T, for drop check
+    _marker: PhantomData<Box<Node<T>>>,
+}
+
+// NonNull is covariant, so LinkedList<&'static str> is a LinkedList<&'a str>
+type Link<T> = Option<NonNull<Node<T>>>;
+
+struct Node<T> {
+    front: Link<T>,
+    back: Link<T>,
+    elem: T,
+}
+
+pub struct Iter<'a, T> {
+    front: Link<T>,
+    back: Link<T>,
+    len: usize,
+    _marker: PhantomData<&'a T>,
+}
+
+pub struct IterMut<'a, T> {
+    front: Link<T>,
+    back: Link<T>,
+    len: usize,
+    _marker: PhantomData<&'a mut T>,
+}
+
+pub struct IntoIter<T>(LinkedList<T>);
+
+// A cursor points at an element, or at the "ghost" between the back and the
+// front. Moving past either end lands on the ghost, moving again wraps around.
+pub struct CursorMut<'a, T> {
+    list: &'a mut LinkedList<T>,
+    cur: Link<T>,
+    // index of cur, the ghost is at list.len
+    index: usize,
+}
+
+unsafe impl<T: Send> Send for LinkedList<T> {}
+unsafe impl<T: Sync> Sync for LinkedList<T> {}
+unsafe impl<T: Sync> Send for Iter<'_, T> {}
+unsafe impl<T: Sync> Sync for Iter<'_, T> {}
+unsafe impl<T: Send> Send for IterMut<'_, T> {}
+unsafe impl<T: Sync> Sync for IterMut<'_, T> {}
+unsafe impl<T: Send> Send for CursorMut<'_, T> {}
+unsafe impl<T: Sync> Sync for CursorMut<'_, T> {}
+
+impl<T> LinkedList<T> {
+    pub fn new() -> Self {
+        Self {
+            front: None,
+            back: None,
+            len: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+
+    pub fn push_front(&mut self, elem: T) {
+        let node = new_node(elem);
+        unsafe {
+            match self.front {
+                Some(front) => {
+                    (*front.as_ptr()).front = Some(node);
+                    (*node.as_ptr()).back = Some(front);
+                }
+                None => self.back = Some(node),
+            }
+        }
+        self.front = Some(node);
+        self.len += 1;
+    }
+
+    pub fn push_back(&mut self, elem: T) {
+        let node = new_node(elem);
+        unsafe {
+            match self.back {
+                Some(back) => {
+                    (*back.as_ptr()).back = Some(node);
+                    (*node.as_ptr()).front = Some(back);
+                }
+                None => self.front = Some(node),
+            }
+        }
+        self.back = Some(node);
+        self.len += 1;
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.front.map(|front| unsafe {
+            let node = Box::from_raw(front.as_ptr());
+            self.front = node.back;
+            match self.front {
+                Some(new) => (*new.as_ptr()).front = None,
+                None => self.back = None,
+            }
+            self.len -= 1;
+            node.elem
+        })
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.back.map(|back| unsafe {
+            let node = Box::from_raw(back.as_ptr());
+            self.back = node.front;
+            match self.back {
+                Some(new) => (*new.as_ptr()).back = None,
+                None => self.front = None,
+            }
+            self.len -= 1;
+            node.elem
+        })
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        unsafe { self.front.map(|node| &(*node.as_ptr()).elem) }
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        unsafe { self.front.map(|node| &mut (*node.as_ptr()).elem) }
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        unsafe { self.back.map(|node| &(*node.as_ptr()).elem) }
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        unsafe { self.back.map(|node| &mut (*node.as_ptr()).elem) }
+    }
+
+    // move all elements of other to the back, O(1)
+    pub fn append(&mut self, other: &mut Self) {
+        let mut cursor = self.cursor_back_mut();
+        cursor.move_next();
+        cursor.splice_before(std::mem::take(other));
+    }
+
+    // split off [at, len), walking from the nearer end
+    pub fn split_off(&mut self, at: usize) -> Self {
+        assert!(at <= self.len, "split index out of bounds");
+        let len = self.len;
+        let mut cursor = if at <= len / 2 {
+            let mut cursor = self.cursor_front_mut();
+            for _ in 0..at {
+                cursor.move_next();
+            }
+            cursor
+        } else {
+            let mut cursor = self.cursor_back_mut();
+            cursor.move_next();
+            for _ in at..len {
+                cursor.move_prev();
+            }
+            cursor
+        };
+
+        // at the ghost split_before would take the whole list
+        if cursor.index().is_none() {
+            return Self::new();
+        }
+        let front = cursor.split_before();
+        std::mem::replace(self, front)
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            front: self.front,
+            back: self.back,
+            len: self.len,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            front: self.front,
+            back: self.back,
+            len: self.len,
+            _marker: PhantomData,
+        }
+    }
+
+    // the cursor starts on the first element, or the ghost if it is empty
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            cur: self.front,
+            index: 0,
+            list: self,
+        }
+    }
+
+    pub fn cursor_back_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            cur: self.back,
+            index: self.len.saturating_sub(1),
+            list: self,
+        }
+    }
+
+    // take all nodes out, leaving the list empty
+    fn take(&mut self) -> (Link<T>, Link<T>, usize) {
+        (
+            self.front.take(),
+            self.back.take(),
+            std::mem::replace(&mut self.len, 0),
+        )
+    }
+}
+
+// Handle to a node for the caches in this module, it stays valid until the
+// node is removed, and may be moved between lists with unlink/link_front.
+pub(super) struct NodeRef<T>(NonNull<Node<T>>);
+
+impl<T> Clone for NodeRef<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for NodeRef<T> {}
+
+impl<T> NodeRef<T> {
+    pub(super) unsafe fn get<'a>(self) -> &'a T {
+        &(*self.0.as_ptr()).elem
+    }
+
+    pub(super) unsafe fn get_mut<'a>(self) -> &'a mut T {
+        &mut (*self.0.as_ptr()).elem
+    }
+
+    // free a node that is not in a list
+    pub(super) unsafe fn into_inner(self) -> T {
+        Box::from_raw(self.0.as_ptr()).elem
+    }
+}
+
+impl<T> LinkedList<T> {
+    pub(super) fn push_front_ref(&mut self, elem: T) -> NodeRef<T> {
+        let node = NodeRef(new_node(elem));
+        unsafe { self.link_front(node) };
+        node
+    }
+
+    pub(super) fn front_ref(&self) -> Option<NodeRef<T>> {
+        self.front.map(NodeRef)
+    }
+
+    pub(super) fn back_ref(&self) -> Option<NodeRef<T>> {
+        self.back.map(NodeRef)
+    }
+
+    // the node behind this one, it must be in the list
+    pub(super) unsafe fn next_ref(&self, node: NodeRef<T>) -> Option<NodeRef<T>> {
+        (*node.0.as_ptr()).back.map(NodeRef)
+    }
+
+    // insert elem right behind at, which must be in the list
+    pub(super) unsafe fn push_after_ref(&mut self, at: NodeRef<T>, elem: T) -> NodeRef<T> {
+        let node = new_node(elem);
+        let at = at.0;
+        let next = (*at.as_ptr()).back;
+        (*node.as_ptr()).front = Some(at);
+        (*node.as_ptr()).back = next;
+        match next {
+            Some(next) => (*next.as_ptr()).front = Some(node),
+            None => self.back = Some(node),
+        }
+        (*at.as_ptr()).back = Some(node);
+        self.len += 1;
+        NodeRef(node)
+    }
+
+    // the node must not be in a list
+    pub(super) unsafe fn link_front(&mut self, node: NodeRef<T>) {
+        let node = node.0;
+        (*node.as_ptr()).front = None;
+        (*node.as_ptr()).back = self.front;
+        match self.front {
+            Some(front) => (*front.as_ptr()).front = Some(node),
+            None => self.back = Some(node),
+        }
+        self.front = Some(node);
+        self.len += 1;
+    }
+
+    // take the node out of this list, it must be in it
+    pub(super) unsafe fn unlink(&mut self, node: NodeRef<T>) {
+        let node = &mut *node.0.as_ptr();
+        match node.front {
+            Some(prev) => (*prev.as_ptr()).back = node.back,
+            None => self.front = node.back,
+        }
+        match node.back {
+            Some(next) => (*next.as_ptr()).front = node.front,
+            None => self.back = node.front,
+        }
+        node.front = None;
+        node.back = None;
+        self.len -= 1;
+    }
+
+    pub(super) unsafe fn remove(&mut self, node: NodeRef<T>) -> T {
+        self.unlink(node);
+        node.into_inner()
+    }
+}
+
+fn new_node<T>(elem: T) -> NonNull<Node<T>> {
+    let node = Box::new(Node {
+        front: None,
+        back: None,
+        elem,
+    });
+    unsafe { NonNull::new_unchecked(Box::into_raw(node)) }
+}
+
+impl<T> Drop for LinkedList<T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T> Default for LinkedList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> Clone for LinkedList<T> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T> Extend<T> for LinkedList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            self.push_back(elem);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for LinkedList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for LinkedList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for LinkedList<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other)
+    }
+}
+
+impl<T: Eq> Eq for LinkedList<T> {}
+
+impl<T: Hash> Hash for LinkedList<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len.hash(state);
+        for elem in self {
+            elem.hash(state);
+        }
+    }
+}
+
+impl<T> IntoIterator for LinkedList<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a LinkedList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut LinkedList<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        // the ends only meet in the middle, stop by counting
+        if self.len == 0 {
+            return None;
+        }
+        self.front.map(|node| unsafe {
+            self.len -= 1;
+            self.front = (*node.as_ptr()).back;
+            &(*node.as_ptr()).elem
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.back.map(|node| unsafe {
+            self.len -= 1;
+            self.back = (*node.as_ptr()).front;
+            &(*node.as_ptr()).elem
+        })
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.front.map(|node| unsafe {
+            self.len -= 1;
+            self.front = (*node.as_ptr()).back;
+            &mut (*node.as_ptr()).elem
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for IterMut<'a, T> {
+    fn next_back(&mut self) -> Option<&'a mut T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.back.map(|node| unsafe {
+            self.len -= 1;
+            self.back = (*node.as_ptr()).front;
+            &mut (*node.as_ptr()).elem
+        })
+    }
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.0.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.0.pop_back()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<'a, T> CursorMut<'a, T> {
+    pub fn index(&self) -> Option<usize> {
+        self.cur.map(|_| self.index)
+    }
+
+    pub fn move_next(&mut self) {
+        match self.cur {
+            None => {
+                self.cur = self.list.front;
+                self.index = 0;
+            }
+            Some(cur) => unsafe {
+                self.cur = (*cur.as_ptr()).back;
+                self.index += 1;
+            },
+        }
+    }
+
+    pub fn move_prev(&mut self) {
+        match self.cur {
+            None => {
+                self.cur = self.list.back;
+                self.index = self.list.len.saturating_sub(1);
+            }
+            Some(cur) => unsafe {
+                self.cur = (*cur.as_ptr()).front;
+                self.index = match self.cur {
+                    Some(_) => self.index - 1,
+                    None => self.list.len,
+                };
+            },
+        }
+    }
+
+    pub fn current(&mut self) -> Option<&mut T> {
+        unsafe { self.cur.map(|node| &mut (*node.as_ptr()).elem) }
+    }
+
+    pub fn peek_next(&mut self) -> Option<&mut T> {
+        unsafe {
+            let next = match self.cur {
+                None => self.list.front,
+                Some(cur) => (*cur.as_ptr()).back,
+            };
+            next.map(|node| &mut (*node.as_ptr()).elem)
+        }
+    }
+
+    pub fn peek_prev(&mut self) -> Option<&mut T> {
+        unsafe {
+            let prev = match self.cur {
+                None => self.list.back,
+                Some(cur) => (*cur.as_ptr()).front,
+            };
+            prev.map(|node| &mut (*node.as_ptr()).elem)
+        }
+    }
+
+    // at the ghost this inserts at the front
+    pub fn insert_after(&mut self, elem: T) {
+        let mut list = LinkedList::new();
+        list.push_back(elem);
+        self.splice_after(list);
+    }
+
+    // at the ghost this inserts at the back
+    pub fn insert_before(&mut self, elem: T) {
+        let mut list = LinkedList::new();
+        list.push_back(elem);
+        self.splice_before(list);
+    }
+
+    // remove the current element and move to the next one
+    pub fn remove_current(&mut self) -> Option<T> {
+        let cur = self.cur?;
+        unsafe {
+            let node = Box::from_raw(cur.as_ptr());
+            match node.front {
+                Some(prev) => (*prev.as_ptr()).back = node.back,
+                None => self.list.front = node.back,
+            }
+            match node.back {
+                Some(next) => (*next.as_ptr()).front = node.front,
+                None => self.list.back = node.front,
+            }
+            self.cur = node.back;
+            self.list.len -= 1;
+            Some(node.elem)
+        }
+    }
+
+    // everything after the cursor, all of the list at the ghost
+    pub fn split_after(&mut self) -> LinkedList<T> {
+        let cur = match self.cur {
+            None => return self.take_all(),
+            Some(cur) => cur,
+        };
+        unsafe {
+            match (*cur.as_ptr()).back.take() {
+                None => LinkedList::new(),
+                Some(next) => {
+                    (*next.as_ptr()).front = None;
+                    let len = self.list.len - self.index - 1;
+                    self.list.len = self.index + 1;
+                    LinkedList {
+                        front: Some(next),
+                        back: self.list.back.replace(cur),
+                        len,
+                        _marker: PhantomData,
+                    }
+                }
+            }
+        }
+    }
+
+    // everything before the cursor, all of the list at the ghost
+    pub fn split_before(&mut self) -> LinkedList<T> {
+        let cur = match self.cur {
+            None => return self.take_all(),
+            Some(cur) => cur,
+        };
+        unsafe {
+            match (*cur.as_ptr()).front.take() {
+                None => LinkedList::new(),
+                Some(prev) => {
+                    (*prev.as_ptr()).back = None;
+                    let len = std::mem::replace(&mut self.index, 0);
+                    self.list.len -= len;
+                    LinkedList {
+                        front: self.list.front.replace(cur),
+                        back: Some(prev),
+                        len,
+                        _marker: PhantomData,
+                    }
+                }
+            }
+        }
+    }
+
+    fn take_all(&mut self) -> LinkedList<T> {
+        let (front, back, len) = self.list.take();
+        self.index = 0;
+        LinkedList {
+            front,
+            back,
+            len,
+            _marker: PhantomData,
+        }
+    }
+
+    // move the elements of other after the cursor, at the ghost to the front
+    pub fn splice_after(&mut self, mut other: LinkedList<T>) {
+        let (front, back, len) = match other.take() {
+            (Some(front), Some(back), len) => (front, back, len),
+            _ => return,
+        };
+        unsafe {
+            let next = match self.cur {
+                None => {
+                    self.index += len;
+                    self.list.front.replace(front)
+                }
+                Some(cur) => {
+                    (*front.as_ptr()).front = Some(cur);
+                    (*cur.as_ptr()).back.replace(front)
+                }
+            };
+            match next {
+                Some(next) => {
+                    (*back.as_ptr()).back = Some(next);
+                    (*next.as_ptr()).front = Some(back);
+                }
+                None => self.list.back = Some(back),
+            }
+        }
+        self.list.len += len;
+    }
+
+    // move the elements of other before the cursor, at the ghost to the back
+    pub fn splice_before(&mut self, mut other: LinkedList<T>) {
+        let (front, back, len) = match other.take() {
+            (Some(front), Some(back), len) => (front, back, len),
+            _ => return,
+        };
+        unsafe {
+            let prev = match self.cur {
+                None => self.list.back.replace(back),
+                Some(cur) => {
+                    (*back.as_ptr()).back = Some(cur);
+                    (*cur.as_ptr()).front.replace(back)
+                }
+            };
+            match prev {
+                Some(prev) => {
+                    (*prev.as_ptr()).back = Some(front);
+                    (*front.as_ptr()).front = Some(prev);
+                }
+                None => self.list.front = Some(front),
+            }
+        }
+        self.index += len;
+        self.list.len += len;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LinkedList;
+    use rand::random;
+
+    fn check(my_list: &LinkedList<i32>, std_list: &std::collections::LinkedList<i32>) {
+        assert_eq!(my_list.len(), std_list.len());
+        assert!(my_list.iter().eq(std_list.iter()));
+        assert!(my_list.iter().rev().eq(std_list.iter().rev()));
+    }
+
+    #[test]
+    fn test() {
+        let mut my_list = LinkedList::new();
+        let mut std_list = std::collections::LinkedList::new();
+        for _ in 0..10000 {
+            match random::<u32>() % 6 {
+                0 => assert_eq!(my_list.pop_front(), std_list.pop_front()),
+                1 => assert_eq!(my_list.pop_back(), std_list.pop_back()),
+                2 => {
+                    let val = random();
+                    my_list.push_front(val);
+                    std_list.push_front(val);
+                }
+                3 => {
+                    let at = random::<usize>() % (std_list.len() + 1);
+                    let mut my_tail = my_list.split_off(at);
+                    let mut std_tail = std_list.split_off(at);
+                    check(&my_tail, &std_tail);
+                    my_list.append(&mut my_tail);
+                    std_list.append(&mut std_tail);
+                    assert!(my_tail.is_empty());
+                }
+                _ => {
+                    let val = random();
+                    my_list.push_back(val);
+                    std_list.push_back(val);
+                }
+            }
+            assert_eq!(my_list.front(), std_list.front());
+            assert_eq!(my_list.back(), std_list.back());
+        }
+        check(&my_list, &std_list);
+
+        for elem in my_list.iter_mut() {
+            *elem /= 2;
+        }
+        for elem in std_list.iter_mut() {
+            *elem /= 2;
+        }
+        check(&my_list, &std_list);
+        assert!(my_list
+            .clone()
+            .into_iter()
+            .rev()
+            .eq(std_list.into_iter().rev()));
+    }
+
+    #[test]
+    fn cursor() {
+        let mut list: LinkedList<i32> = (1..=3).collect();
+        let mut cursor = list.cursor_front_mut();
+        *cursor.current().unwrap() = 10;
+        cursor.move_next();
+        assert_eq!(cursor.peek_next(), Some(&mut 3));
+        cursor.insert_before(15);
+        cursor.insert_after(25);
+        assert_eq!(cursor.index(), Some(2));
+        assert_eq!(cursor.remove_current(), Some(2));
+        assert_eq!(cursor.current(), Some(&mut 25));
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.index(), None);
+        assert_eq!(cursor.peek_next(), Some(&mut 10));
+        assert_eq!(cursor.peek_prev(), Some(&mut 3));
+        cursor.insert_before(4);
+        cursor.insert_after(0);
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some(&mut 4));
+        assert_eq!(cursor.index(), Some(5));
+        assert_eq!(format!("{:?}", list), "[0, 10, 15, 25, 3, 4]");
+
+        let mut cursor = list.cursor_front_mut();
+        cursor.move_next();
+        cursor.move_next();
+        let after = cursor.split_after();
+        let before = cursor.split_before();
+        assert_eq!(cursor.index(), Some(0));
+        assert!(after.iter().eq(&[25, 3, 4]));
+        assert!(before.iter().eq(&[0, 10]));
+        cursor.splice_before(after);
+        cursor.splice_after(before);
+        assert_eq!(cursor.index(), Some(3));
+        assert_eq!(cursor.remove_current(), Some(15));
+        assert_eq!(cursor.remove_current(), Some(0));
+        assert_eq!(cursor.remove_current(), Some(10));
+        assert_eq!(cursor.remove_current(), None);
+        assert!(list.iter().eq(&[25, 3, 4]));
+        assert!(list.iter().rev().eq(&[4, 3, 25]));
+    }
+
+    #[test]
+    fn send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<LinkedList<i32>>();
+        assert_send_sync::<super::Iter<'_, i32>>();
+        assert_send_sync::<super::IterMut<'_, i32>>();
+        assert_send_sync::<super::CursorMut<'_, i32>>();
+
+        // covariant in T
+        fn shorten<'a>(list: LinkedList<&'static str>) -> LinkedList<&'a str> {
+            list
+        }
+        let list = shorten(std::iter::once("a").collect());
+        let handle = std::thread::spawn(move || list.len());
+        assert_eq!(handle.join().unwrap(), 1);
+    }
+}
@@ -0,0 +1,200 @@
+use super::hazard::Domain;
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+struct Node<T> {
+    // set for every node but the dummy at the head, taken by the dequeue
+    // that makes the node the new dummy
+    elem: UnsafeCell<MaybeUninit<T>>,
+    next: AtomicPtr<Node<T>>,
+}
+
+impl<T> Node<T> {
+    fn new(elem: MaybeUninit<T>) -> *mut Self {
+        Box::into_raw(Box::new(Node {
+            elem: UnsafeCell::new(elem),
+            next: AtomicPtr::new(ptr::null_mut()),
+        }))
+    }
+}
+
+// Multi-producer multi-consumer queue without locks (Michael & Scott, 1996).
+// head always points at a dummy node, the elements are in the nodes after
+// it. Removed dummies are freed through hazard pointers, so a dequeue never
+// reads a freed node.
+pub struct Queue<T> {
+    head: AtomicPtr<Node<T>>,
+    tail: AtomicPtr<Node<T>>,
+    domain: Domain,
+}
+
+unsafe impl<T: Send> Send for Queue<T> {}
+unsafe impl<T: Send> Sync for Queue<T> {}
+
+impl<T> Queue<T> {
+    pub fn new() -> Self {
+        let dummy = Node::new(MaybeUninit::uninit());
+        Self {
+            head: AtomicPtr::new(dummy),
+            tail: AtomicPtr::new(dummy),
+            domain: Domain::new(),
+        }
+    }
+
+    pub fn enqueue(&self, elem: T) {
+        let node = Node::new(MaybeUninit::new(elem));
+        let guard = self.domain.guard();
+        loop {
+            let tail = guard.load(0, &self.tail);
+            let next = unsafe { (*tail).next.load(Ordering::Acquire) };
+            if !next.is_null() {
+                // tail is behind, help the other enqueue
+                let _ = self
+                    .tail
+                    .compare_exchange(tail, next, Ordering::AcqRel, Ordering::Relaxed);
+                continue;
+            }
+            let linked = unsafe {
+                (*tail).next.compare_exchange(
+                    ptr::null_mut(),
+                    node,
+                    Ordering::AcqRel,
+                    Ordering::Relaxed,
+                )
+            };
+            if linked.is_ok() {
+                let _ = self
+                    .tail
+                    .compare_exchange(tail, node, Ordering::AcqRel, Ordering::Relaxed);
+                return;
+            }
+        }
+    }
+
+    pub fn dequeue(&self) -> Option<T> {
+        let guard = self.domain.guard();
+        loop {
+            let head = guard.load(0, &self.head);
+            let next = unsafe { (*head).next.load(Ordering::Acquire) };
+            guard.protect(1, next);
+            // head still being the head keeps next from being retired
+            if self.head.load(Ordering::SeqCst) != head {
+                continue;
+            }
+            if next.is_null() {
+                return None;
+            }
+            let tail = self.tail.load(Ordering::Acquire);
+            if head == tail {
+                let _ = self
+                    .tail
+                    .compare_exchange(tail, next, Ordering::AcqRel, Ordering::Relaxed);
+                continue;
+            }
+            if self
+                .head
+                .compare_exchange(head, next, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+            {
+                // only the winner reads the element of the new dummy
+                let elem = unsafe { (*(*next).elem.get()).assume_init_read() };
+                unsafe { guard.retire(head) };
+                return Some(elem);
+            }
+        }
+    }
+
+    // only a snapshot when other threads use the queue
+    pub fn is_empty(&self) -> bool {
+        let guard = self.domain.guard();
+        let head = guard.load(0, &self.head);
+        unsafe { (*head).next.load(Ordering::Acquire).is_null() }
+    }
+}
+
+impl<T> Default for Queue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for Queue<T> {
+    fn drop(&mut self) {
+        while self.dequeue().is_some() {}
+        unsafe { drop(Box::from_raw(*self.head.get_mut())) };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Queue;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn basic() {
+        let queue = Queue::new();
+        assert_eq!(queue.dequeue(), None);
+        assert!(queue.is_empty());
+        queue.enqueue(1);
+        queue.enqueue(2);
+        assert!(!queue.is_empty());
+        assert_eq!(queue.dequeue(), Some(1));
+        queue.enqueue(3);
+        assert_eq!(queue.dequeue(), Some(2));
+        assert_eq!(queue.dequeue(), Some(3));
+        assert_eq!(queue.dequeue(), None);
+
+        // elements left in the queue are dropped with it
+        let rc = Arc::new(());
+        let queue = Queue::new();
+        for _ in 0..1000 {
+            queue.enqueue(rc.clone());
+        }
+        for _ in 0..500 {
+            queue.dequeue();
+        }
+        drop(queue);
+        assert_eq!(Arc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn mpmc() {
+        const THREADS: usize = 4;
+        const ELEMS: usize = 20000;
+
+        let queue = Queue::new();
+        let sum = AtomicUsize::new(0);
+        let taken = AtomicUsize::new(0);
+        thread::scope(|s| {
+            for t in 0..THREADS {
+                let queue = &queue;
+                s.spawn(move || {
+                    for i in 0..ELEMS {
+                        queue.enqueue((t, i));
+                    }
+                });
+            }
+            for _ in 0..THREADS {
+                let (queue, sum, taken) = (&queue, &sum, &taken);
+                s.spawn(move || {
+                    // each producer's elements come out in order
+                    let mut last = [None; THREADS];
+                    while taken.load(Ordering::Relaxed) < THREADS * ELEMS {
+                        if let Some((t, i)) = queue.dequeue() {
+                            assert!(last[t].map_or(true, |last| last < i));
+                            last[t] = Some(i);
+                            sum.fetch_add(i, Ordering::Relaxed);
+                            taken.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+        assert_eq!(sum.into_inner(), THREADS * ELEMS * (ELEMS - 1) / 2);
+        assert!(queue.is_empty());
+    }
+}
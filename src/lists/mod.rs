@@ -1,4 +1,13 @@
 // code of learning RUST with too many lists
+mod cache;
+pub mod channel;
 mod deque;
+mod epoch;
+mod hazard;
+mod linked_list;
+pub(crate) mod lockfree;
+mod persistent;
 mod queue;
+mod reclaim;
+mod skiplist;
 mod stack;
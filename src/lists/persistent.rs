@@ -0,0 +1,661 @@
+use super::stack::List;
+use crate::ptr_kind::{ArcK, PointerKind, RcK};
+use std::fmt;
+use std::iter::FromIterator;
+use std::sync::{Mutex, OnceLock};
+
+// a side of the deque may get this many times longer than the other
+const C: usize = 3;
+
+// shared, memoized list cell, P picks Rc or Arc for it
+struct Stream<T, P: PointerKind>(<P as PointerKind>::Ptr<Lazy<T, P>>);
+
+enum Cell<T, P: PointerKind> {
+    Nil,
+    Cons(T, Stream<T, P>),
+}
+
+// Memoized suspension: the thunk runs on the first force, and every version
+// sharing the stream sees the result. This is what keeps the bounds below
+// when old versions are used again.
+struct Lazy<T, P: PointerKind> {
+    cell: OnceLock<Cell<T, P>>,
+    thunk: Mutex<Option<Thunk<T, P>>>,
+}
+
+// the suspended stream operations, kept as data so they are Send when T is
+enum Thunk<T, P: PointerKind> {
+    // a ++ b
+    Append(Stream<T, P>, Stream<T, P>),
+    // the elements of the stack, bottom first, all at once
+    Reverse(List<T, P>),
+    // the first n elements
+    Take(usize, Stream<T, P>),
+    // f ++ reverse(r) ++ a, one element of f and C of r per step
+    RotateRev(Stream<T, P>, Stream<T, P>, Stream<T, P>),
+    // f ++ reverse(drop(j, r)), C elements of r dropped per step
+    RotateDrop(Stream<T, P>, usize, Stream<T, P>),
+}
+
+impl<T: Clone, P: PointerKind> Clone for Cell<T, P> {
+    fn clone(&self) -> Self {
+        match self {
+            Cell::Nil => Cell::Nil,
+            Cell::Cons(x, s) => Cell::Cons(x.clone(), s.clone()),
+        }
+    }
+}
+
+impl<T, P: PointerKind> Clone for Stream<T, P> {
+    fn clone(&self) -> Self {
+        Stream(self.0.clone())
+    }
+}
+
+impl<T, P: PointerKind> Drop for Lazy<T, P> {
+    // walk down the forced cells, stop at the first one that is shared
+    fn drop(&mut self) {
+        let mut next = match self.cell.take() {
+            Some(Cell::Cons(_, tail)) => tail,
+            _ => return,
+        };
+        while let Some(mut lazy) = P::into_inner(next.0) {
+            next = match lazy.cell.take() {
+                Some(Cell::Cons(_, tail)) => tail,
+                _ => return,
+            };
+        }
+    }
+}
+
+impl<T, P: PointerKind> Stream<T, P> {
+    fn ready(cell: Cell<T, P>) -> Self {
+        Stream(P::new(Lazy {
+            cell: OnceLock::from(cell),
+            thunk: Mutex::new(None),
+        }))
+    }
+
+    fn suspend(thunk: Thunk<T, P>) -> Self {
+        Stream(P::new(Lazy {
+            cell: OnceLock::new(),
+            thunk: Mutex::new(Some(thunk)),
+        }))
+    }
+
+    fn nil() -> Self {
+        Self::ready(Cell::Nil)
+    }
+}
+
+impl<T: Clone, P: PointerKind> Stream<T, P> {
+    fn force(&self) -> &Cell<T, P> {
+        self.0
+            .cell
+            .get_or_init(|| eval(self.0.thunk.lock().unwrap().take().unwrap()))
+    }
+
+    fn head(&self) -> Option<&T> {
+        match self.force() {
+            Cell::Cons(x, _) => Some(x),
+            Cell::Nil => None,
+        }
+    }
+
+    // the tail, or self if it is empty
+    fn tail(&self) -> Self {
+        match self.force() {
+            Cell::Cons(_, tail) => tail.clone(),
+            Cell::Nil => self.clone(),
+        }
+    }
+
+    fn drop_n(&self, n: usize) -> Self {
+        let mut s = self.clone();
+        for _ in 0..n {
+            s = s.tail();
+        }
+        s
+    }
+
+    // reverse(take(n, self)) ++ a
+    fn prepend_rev(&self, n: usize, a: Self) -> Self {
+        let (mut s, mut acc) = (self, a);
+        for _ in 0..n {
+            match s.force() {
+                Cell::Cons(x, tail) => {
+                    acc = Self::ready(Cell::Cons(x.clone(), acc));
+                    s = tail;
+                }
+                Cell::Nil => break,
+            }
+        }
+        acc
+    }
+
+    // the first n elements, front to back
+    fn refs(&self, n: usize) -> Vec<&T> {
+        let (mut s, mut refs) = (self, Vec::with_capacity(n));
+        while refs.len() < n {
+            match s.force() {
+                Cell::Cons(x, tail) => {
+                    refs.push(x);
+                    s = tail;
+                }
+                Cell::Nil => break,
+            }
+        }
+        refs
+    }
+}
+
+fn eval<T: Clone, P: PointerKind>(thunk: Thunk<T, P>) -> Cell<T, P> {
+    match thunk {
+        Thunk::Append(a, b) => match a.force() {
+            Cell::Cons(x, a) => Cell::Cons(x.clone(), Stream::suspend(Thunk::Append(a.clone(), b))),
+            Cell::Nil => b.force().clone(),
+        },
+        Thunk::Reverse(list) => list.iter().fold(Cell::Nil, |cell, x| {
+            Cell::Cons(x.clone(), Stream::ready(cell))
+        }),
+        Thunk::Take(0, _) => Cell::Nil,
+        Thunk::Take(n, s) => match s.force() {
+            Cell::Cons(x, s) => {
+                Cell::Cons(x.clone(), Stream::suspend(Thunk::Take(n - 1, s.clone())))
+            }
+            Cell::Nil => Cell::Nil,
+        },
+        Thunk::RotateRev(f, r, a) => match f.force() {
+            Cell::Cons(x, f) => Cell::Cons(
+                x.clone(),
+                Stream::suspend(Thunk::RotateRev(
+                    f.clone(),
+                    r.drop_n(C),
+                    r.prepend_rev(C, a),
+                )),
+            ),
+            // r has at most C + 1 elements left here
+            Cell::Nil => r.prepend_rev(usize::MAX, a).force().clone(),
+        },
+        Thunk::RotateDrop(f, j, r) => match f.force() {
+            Cell::Cons(x, f) if j >= C => Cell::Cons(
+                x.clone(),
+                Stream::suspend(Thunk::RotateDrop(f.clone(), j - C, r.drop_n(C))),
+            ),
+            _ => eval(Thunk::RotateRev(f.clone(), r.drop_n(j), Stream::nil())),
+        },
+    }
+}
+
+pub struct Iter<'a, T, P: PointerKind + 'a> {
+    front: &'a Stream<T, P>,
+    front_len: usize,
+    // the rest, popped from the end
+    back: Vec<&'a T>,
+}
+
+impl<'a, T: Clone, P: PointerKind> Iterator for Iter<'a, T, P> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<&'a T> {
+        if self.front_len == 0 {
+            return self.back.pop();
+        }
+        self.front_len -= 1;
+        match self.front.force() {
+            Cell::Cons(x, tail) => {
+                self.front = tail;
+                Some(x)
+            }
+            Cell::Nil => unreachable!(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.front_len + self.back.len();
+        (len, Some(len))
+    }
+}
+
+impl<T: Clone, P: PointerKind> ExactSizeIterator for Iter<'_, T, P> {}
+
+// Banker's queue (Okasaki, 6.3.2): the front is a lazy stream, the back a
+// stack. Once the back gets longer than the front it is reversed onto the
+// end of the front, lazily, so push_back and pop_front are O(1) amortized,
+// for any use of old versions.
+pub struct BankersQueue<T, P: PointerKind = RcK> {
+    lenf: usize,
+    f: Stream<T, P>,
+    lenr: usize,
+    r: List<T, P>,
+}
+
+pub type SyncBankersQueue<T> = BankersQueue<T, ArcK>;
+
+impl<T, P: PointerKind> BankersQueue<T, P> {
+    pub fn new() -> Self {
+        BankersQueue {
+            lenf: 0,
+            f: Stream::nil(),
+            lenr: 0,
+            r: List::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lenf + self.lenr
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn check(self) -> Self {
+        if self.lenr <= self.lenf {
+            return self;
+        }
+        BankersQueue {
+            lenf: self.lenf + self.lenr,
+            f: Stream::suspend(Thunk::Append(
+                self.f,
+                Stream::suspend(Thunk::Reverse(self.r)),
+            )),
+            lenr: 0,
+            r: List::new(),
+        }
+    }
+}
+
+impl<T: Clone, P: PointerKind> BankersQueue<T, P> {
+    pub fn front(&self) -> Option<&T> {
+        self.f.head()
+    }
+
+    pub fn push_back(&self, val: T) -> Self {
+        BankersQueue {
+            lenf: self.lenf,
+            f: self.f.clone(),
+            lenr: self.lenr + 1,
+            r: self.r.push(val),
+        }
+        .check()
+    }
+
+    pub fn pop_front(&self) -> Self {
+        if self.lenf == 0 {
+            return Self::new();
+        }
+        BankersQueue {
+            lenf: self.lenf - 1,
+            f: self.f.tail(),
+            lenr: self.lenr,
+            r: self.r.clone(),
+        }
+        .check()
+    }
+
+    pub fn iter(&self) -> Iter<'_, T, P> {
+        Iter {
+            front: &self.f,
+            front_len: self.lenf,
+            back: self.r.iter().collect(),
+        }
+    }
+}
+
+impl<T, P: PointerKind> Clone for BankersQueue<T, P> {
+    fn clone(&self) -> Self {
+        BankersQueue {
+            lenf: self.lenf,
+            f: self.f.clone(),
+            lenr: self.lenr,
+            r: self.r.clone(),
+        }
+    }
+}
+
+impl<T, P: PointerKind> Default for BankersQueue<T, P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone, P: PointerKind> FromIterator<T> for BankersQueue<T, P> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(Self::new(), |queue, val| queue.push_back(val))
+    }
+}
+
+impl<T: Clone + PartialEq, P: PointerKind> PartialEq for BankersQueue<T, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Clone + fmt::Debug, P: PointerKind> fmt::Debug for BankersQueue<T, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T: Clone, P: PointerKind> IntoIterator for &'a BankersQueue<T, P> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, P>;
+    fn into_iter(self) -> Iter<'a, T, P> {
+        self.iter()
+    }
+}
+
+// Real-time deque (Okasaki, 8.4.3): two lazy streams, rebuilt once one side
+// is more than C times longer than the other. The rebuild is a chain of
+// suspensions, and every operation forces a few cells of it through the
+// schedules sf and sr, so all operations are O(1) in the worst case.
+pub struct RealTimeDeque<T, P: PointerKind = RcK> {
+    lenf: usize,
+    f: Stream<T, P>,
+    sf: Stream<T, P>,
+    lenr: usize,
+    r: Stream<T, P>,
+    sr: Stream<T, P>,
+}
+
+pub type SyncRealTimeDeque<T> = RealTimeDeque<T, ArcK>;
+
+impl<T, P: PointerKind> RealTimeDeque<T, P> {
+    pub fn new() -> Self {
+        RealTimeDeque {
+            lenf: 0,
+            f: Stream::nil(),
+            sf: Stream::nil(),
+            lenr: 0,
+            r: Stream::nil(),
+            sr: Stream::nil(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lenf + self.lenr
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // the same deque seen from the back
+    fn flip(self) -> Self {
+        RealTimeDeque {
+            lenf: self.lenr,
+            f: self.r,
+            sf: self.sr,
+            lenr: self.lenf,
+            r: self.f,
+            sr: self.sf,
+        }
+    }
+
+    fn check(self) -> Self {
+        if self.lenr > C * self.lenf + 1 {
+            return self.flip().check().flip();
+        }
+        if self.lenf <= C * self.lenr + 1 {
+            return self;
+        }
+        let len = self.lenf + self.lenr;
+        let i = len / 2;
+        let f = Stream::suspend(Thunk::Take(i, self.f.clone()));
+        let r = Stream::suspend(Thunk::RotateDrop(self.r, i, self.f));
+        RealTimeDeque {
+            lenf: i,
+            f: f.clone(),
+            sf: f,
+            lenr: len - i,
+            r: r.clone(),
+            sr: r,
+        }
+    }
+}
+
+impl<T: Clone, P: PointerKind> RealTimeDeque<T, P> {
+    pub fn front(&self) -> Option<&T> {
+        // with an empty front, the back has at most one element
+        if self.lenf == 0 {
+            self.r.head()
+        } else {
+            self.f.head()
+        }
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        if self.lenr == 0 {
+            self.f.head()
+        } else {
+            self.r.head()
+        }
+    }
+
+    pub fn push_front(&self, val: T) -> Self {
+        RealTimeDeque {
+            lenf: self.lenf + 1,
+            f: Stream::ready(Cell::Cons(val, self.f.clone())),
+            sf: self.sf.tail(),
+            lenr: self.lenr,
+            r: self.r.clone(),
+            sr: self.sr.tail(),
+        }
+        .check()
+    }
+
+    pub fn pop_front(&self) -> Self {
+        if self.lenf == 0 {
+            return Self::new();
+        }
+        RealTimeDeque {
+            lenf: self.lenf - 1,
+            f: self.f.tail(),
+            sf: self.sf.tail().tail(),
+            lenr: self.lenr,
+            r: self.r.clone(),
+            sr: self.sr.tail().tail(),
+        }
+        .check()
+    }
+
+    pub fn push_back(&self, val: T) -> Self {
+        self.clone().flip().push_front(val).flip()
+    }
+
+    pub fn pop_back(&self) -> Self {
+        self.clone().flip().pop_front().flip()
+    }
+
+    pub fn iter(&self) -> Iter<'_, T, P> {
+        Iter {
+            front: &self.f,
+            front_len: self.lenf,
+            back: self.r.refs(self.lenr),
+        }
+    }
+}
+
+impl<T, P: PointerKind> Clone for RealTimeDeque<T, P> {
+    fn clone(&self) -> Self {
+        RealTimeDeque {
+            lenf: self.lenf,
+            f: self.f.clone(),
+            sf: self.sf.clone(),
+            lenr: self.lenr,
+            r: self.r.clone(),
+            sr: self.sr.clone(),
+        }
+    }
+}
+
+impl<T, P: PointerKind> Default for RealTimeDeque<T, P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone, P: PointerKind> FromIterator<T> for RealTimeDeque<T, P> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(Self::new(), |deque, val| deque.push_back(val))
+    }
+}
+
+impl<T: Clone + PartialEq, P: PointerKind> PartialEq for RealTimeDeque<T, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Clone + fmt::Debug, P: PointerKind> fmt::Debug for RealTimeDeque<T, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T: Clone, P: PointerKind> IntoIterator for &'a RealTimeDeque<T, P> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, P>;
+    fn into_iter(self) -> Iter<'a, T, P> {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BankersQueue, RealTimeDeque, SyncBankersQueue, SyncRealTimeDeque};
+    use std::collections::VecDeque;
+    use std::thread;
+
+    #[test]
+    fn queue() {
+        let queue: BankersQueue<_> = (0..10).collect();
+        assert_eq!(queue.len(), 10);
+        assert_eq!(queue.front(), Some(&0));
+        let popped = queue.pop_front().pop_front().push_back(10);
+        assert_eq!(format!("{:?}", popped), "[2, 3, 4, 5, 6, 7, 8, 9, 10]");
+        assert_eq!(
+            queue.iter().copied().collect::<Vec<_>>(),
+            (0..10).collect::<Vec<_>>()
+        );
+
+        let mut empty = queue.clone();
+        while !empty.is_empty() {
+            empty = empty.pop_front();
+        }
+        assert_eq!(empty.front(), None);
+        assert!(empty.pop_front().is_empty());
+    }
+
+    #[test]
+    fn deque() {
+        let deque: RealTimeDeque<_> = (0..10).collect();
+        assert_eq!(deque.front(), Some(&0));
+        assert_eq!(deque.back(), Some(&9));
+        let changed = deque.pop_back().push_front(-1).pop_front().pop_front();
+        assert_eq!(format!("{:?}", changed), "[1, 2, 3, 4, 5, 6, 7, 8]");
+        assert_eq!(deque.iter().len(), 10);
+
+        let one = RealTimeDeque::<_>::new().push_back(1);
+        assert_eq!((one.front(), one.back()), (Some(&1), Some(&1)));
+        assert!(one.pop_front().is_empty());
+        assert!(one.pop_back().is_empty());
+    }
+
+    // random operations on random old versions, checked against copies
+    #[test]
+    fn persistence() {
+        let mut queues = vec![(BankersQueue::<u32>::new(), VecDeque::new())];
+        let mut deques = vec![(RealTimeDeque::<u32>::new(), VecDeque::new())];
+        for _ in 0..5000 {
+            let i = rand::random::<usize>() % queues.len();
+            let (queue, std) = &queues[i];
+            let mut std = std.clone();
+            let queue = if rand::random::<u8>() % 3 == 0 {
+                std.pop_front();
+                queue.pop_front()
+            } else {
+                let val = rand::random();
+                std.push_back(val);
+                queue.push_back(val)
+            };
+            assert_eq!(queue.front(), std.front());
+            queues.push((queue, std));
+
+            let i = rand::random::<usize>() % deques.len();
+            let (deque, std) = &deques[i];
+            let mut std = std.clone();
+            let val = rand::random();
+            let deque = match rand::random::<u8>() % 5 {
+                0 => {
+                    std.pop_front();
+                    deque.pop_front()
+                }
+                1 => {
+                    std.pop_back();
+                    deque.pop_back()
+                }
+                2 => {
+                    std.push_front(val);
+                    deque.push_front(val)
+                }
+                _ => {
+                    std.push_back(val);
+                    deque.push_back(val)
+                }
+            };
+            assert_eq!((deque.front(), deque.back()), (std.front(), std.back()));
+            deques.push((deque, std));
+        }
+        for (queue, std) in &queues {
+            assert_eq!(queue.len(), std.len());
+            assert!(queue.iter().eq(std.iter()));
+        }
+        for (deque, std) in &deques {
+            assert_eq!(deque.len(), std.len());
+            assert!(deque.iter().eq(std.iter()));
+        }
+    }
+
+    #[test]
+    fn long() {
+        let mut queue = BankersQueue::<_>::new();
+        let mut deque = RealTimeDeque::<_>::new();
+        for i in 0..200_000 {
+            queue = queue.push_back(i);
+            deque = deque.push_front(i);
+        }
+        for i in 0..100_000 {
+            assert_eq!(queue.front(), Some(&i));
+            assert_eq!(deque.back(), Some(&i));
+            queue = queue.pop_front();
+            deque = deque.pop_back();
+        }
+    }
+
+    #[test]
+    fn sync() {
+        let queue: SyncBankersQueue<_> = (0..100).collect();
+        let deque: SyncRealTimeDeque<_> = (0..100).collect();
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let (queue, deque) = (queue.clone(), deque.clone());
+                thread::spawn(move || {
+                    let queue = queue.pop_front().push_back(100 + i);
+                    let deque = deque.pop_back().push_front(100 + i);
+                    assert!(queue.iter().copied().eq((1..100).chain(Some(100 + i))));
+                    assert!(deque
+                        .iter()
+                        .copied()
+                        .eq(Some(100 + i).into_iter().chain(0..99)));
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert!(queue.iter().copied().eq(0..100));
+    }
+}
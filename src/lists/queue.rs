@@ -1,18 +1,33 @@
+use std::fmt;
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+use std::mem;
 use std::ptr;
 
 pub struct Queue<T> {
     head: Option<Box<Node<T>>>,
     tail: *mut Node<T>,
+    len: usize,
 }
 
+// owns its nodes like a Box, tail only points into them
+unsafe impl<T: Send> Send for Queue<T> {}
+unsafe impl<T: Sync> Sync for Queue<T> {}
+
 pub struct IntoIter<T>(Queue<T>);
 
 pub struct Iter<'a, T> {
     next: Option<&'a Node<T>>,
+    next_back: Option<&'a Node<T>>,
+    len: usize,
 }
 
+// raw pointers, both ends may point to the same node
 pub struct IterMut<'a, T> {
-    next: Option<&'a mut Node<T>>,
+    next: *mut Node<T>,
+    next_back: *mut Node<T>,
+    len: usize,
+    _marker: PhantomData<&'a mut T>,
 }
 
 struct Node<T> {
@@ -26,9 +41,18 @@ impl<T> Queue<T> {
         Queue {
             head: None,
             tail: ptr::null_mut(),
+            len: 0,
         }
     }
 
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
     pub fn enqueue(&mut self, elem: T) {
         let mut tail = Box::new(Node {
             elem,
@@ -45,19 +69,40 @@ impl<T> Queue<T> {
             }
         }
         self.tail = new_tail;
+        self.len += 1;
     }
 
     pub fn dequeue(&mut self) -> Option<T> {
         self.head.take().map(|mut node| {
             self.head = node.next.take();
-            if self.head.is_none() {
-                self.tail = ptr::null_mut();
+            match self.head.as_mut() {
+                Some(head) => head.prev = ptr::null_mut(),
+                None => self.tail = ptr::null_mut(),
             }
+            self.len -= 1;
 
             node.elem
         })
     }
 
+    // take back the element enqueued last
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.tail.is_null() {
+            return None;
+        }
+        unsafe {
+            let prev = (*self.tail).prev;
+            let node = if prev.is_null() {
+                self.head.take()
+            } else {
+                (*prev).next.take()
+            };
+            self.tail = prev;
+            self.len -= 1;
+            node.map(|node| node.elem)
+        }
+    }
+
     pub fn peek(&self) -> Option<&T> {
         self.head.as_ref().map(|head| &head.elem)
     }
@@ -66,6 +111,65 @@ impl<T> Queue<T> {
         self.head.as_mut().map(|head| &mut head.elem)
     }
 
+    pub fn peek_back(&self) -> Option<&T> {
+        unsafe { self.tail.as_ref().map(|tail| &tail.elem) }
+    }
+
+    pub fn peek_back_mut(&mut self) -> Option<&mut T> {
+        unsafe { self.tail.as_mut().map(|tail| &mut tail.elem) }
+    }
+
+    // move all elements of other to the back, in O(1)
+    pub fn append(&mut self, other: &mut Self) {
+        let mut head = match other.head.take() {
+            Some(head) => head,
+            None => return,
+        };
+        if self.tail.is_null() {
+            self.head = Some(head);
+        } else {
+            head.prev = self.tail;
+            unsafe { (*self.tail).next = Some(head) };
+        }
+        self.tail = mem::replace(&mut other.tail, ptr::null_mut());
+        self.len += mem::take(&mut other.len);
+    }
+
+    // split into [0, at) and [at, len), walking from the nearer end
+    pub fn split_off(&mut self, at: usize) -> Self {
+        assert!(at <= self.len, "split index out of bounds");
+        if at == 0 {
+            return mem::take(self);
+        }
+        if at == self.len {
+            return Self::new();
+        }
+        unsafe {
+            // the last node staying in self
+            let mut last = self.tail;
+            if at - 1 < self.len - at {
+                last = self.head.as_deref_mut().unwrap();
+                for _ in 0..at - 1 {
+                    last = (*last).next.as_deref_mut().unwrap();
+                }
+            } else {
+                for _ in 0..self.len - at {
+                    last = (*last).prev;
+                }
+            }
+            let mut head = (*last).next.take().unwrap();
+            head.prev = ptr::null_mut();
+            let other = Queue {
+                head: Some(head),
+                tail: self.tail,
+                len: self.len - at,
+            };
+            self.tail = last;
+            self.len = at;
+            other
+        }
+    }
+
     pub fn into_iter(self) -> IntoIter<T> {
         IntoIter(self)
     }
@@ -73,12 +177,20 @@ impl<T> Queue<T> {
     pub fn iter(&self) -> Iter<'_, T> {
         Iter {
             next: self.head.as_deref(),
+            next_back: unsafe { self.tail.as_ref() },
+            len: self.len,
         }
     }
 
     pub fn iter_mut(&mut self) -> IterMut<'_, T> {
         IterMut {
-            next: self.head.as_deref_mut(),
+            next: self
+                .head
+                .as_deref_mut()
+                .map_or(ptr::null_mut(), |head| head as *mut _),
+            next_back: self.tail,
+            len: self.len,
+            _marker: PhantomData,
         }
     }
 }
@@ -92,36 +204,158 @@ impl<T> Drop for Queue<T> {
     }
 }
 
+impl<T> Default for Queue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> Clone for Queue<T> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T> Extend<T> for Queue<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            self.enqueue(elem);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for Queue<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut queue = Self::new();
+        queue.extend(iter);
+        queue
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Queue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<T> IntoIterator for Queue<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Queue<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut Queue<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+    fn into_iter(self) -> IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
 impl<T> Iterator for IntoIter<T> {
     type Item = T;
     fn next(&mut self) -> Option<T> {
         self.0.dequeue()
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
 }
 
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.0.pop_back()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
 impl<'a, T> Iterator for Iter<'a, T> {
     type Item = &'a T;
     fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
         self.next.take().map(|next| {
             self.next = next.next.as_deref();
             &next.elem
         })
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        self.next_back.take().map(|back| {
+            self.next_back = unsafe { back.prev.as_ref() };
+            &back.elem
+        })
+    }
 }
 
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
 impl<'a, T> Iterator for IterMut<'a, T> {
     type Item = &'a mut T;
     fn next(&mut self) -> Option<Self::Item> {
-        self.next.take().map(|next| {
-            self.next = next.next.as_deref_mut();
-            &mut next.elem
-        })
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        unsafe {
+            let node = self.next;
+            // don't touch a node next_back may have handed out already
+            if self.len > 0 {
+                self.next = (*node).next.as_deref_mut().unwrap();
+            }
+            Some(&mut (*node).elem)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
     }
 }
 
+impl<'a, T> DoubleEndedIterator for IterMut<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        unsafe {
+            let node = self.next_back;
+            self.next_back = (*node).prev;
+            Some(&mut (*node).elem)
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
 #[cfg(test)]
 mod test {
     use super::Queue;
+    use std::collections::VecDeque;
 
     #[test]
     fn basic() {
@@ -192,4 +426,104 @@ mod test {
         assert_eq!(iter.next(), None);
         assert_eq!(queue.peek(), Some(&100));
     }
+
+    #[test]
+    fn double_ended() {
+        let mut queue: Queue<_> = (0..6).collect();
+        assert_eq!(queue.len(), 6);
+        assert_eq!(queue.peek_back(), Some(&5));
+        assert_eq!(queue.pop_back(), Some(5));
+        *queue.peek_back_mut().unwrap() = 40;
+        assert_eq!(format!("{:?}", queue), "[0, 1, 2, 3, 40]");
+
+        let mut iter = queue.iter();
+        assert_eq!(iter.next_back(), Some(&40));
+        assert_eq!(iter.next(), Some(&0));
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.rev().collect::<Vec<_>>(), vec![&3, &2, &1]);
+
+        let mut iter = queue.iter_mut();
+        let (front, back) = (iter.next().unwrap(), iter.next_back().unwrap());
+        *front += 100;
+        *back += 100;
+        for elem in iter {
+            *elem *= 10;
+        }
+        let clone = queue.clone();
+        assert_eq!(
+            clone.into_iter().rev().collect::<Vec<_>>(),
+            vec![140, 30, 20, 10, 100]
+        );
+
+        while queue.pop_back().is_some() {}
+        assert!(queue.is_empty());
+        assert_eq!(queue.peek(), None);
+        queue.enqueue(1);
+        assert_eq!(queue.dequeue(), Some(1));
+    }
+
+    #[test]
+    fn append_split() {
+        let mut queue: Queue<_> = (0..5).collect();
+        let mut other: Queue<_> = (5..10).collect();
+        queue.append(&mut other);
+        assert!(other.is_empty());
+        assert_eq!(
+            queue.iter().copied().collect::<Vec<_>>(),
+            (0..10).collect::<Vec<_>>()
+        );
+
+        for at in 0..=10 {
+            let mut queue = queue.clone();
+            let mut back = queue.split_off(at);
+            assert_eq!(queue.len(), at);
+            assert_eq!(back.len(), 10 - at);
+            assert_eq!(
+                queue.iter().rev().copied().collect::<Vec<_>>(),
+                (0..at).rev().collect::<Vec<_>>()
+            );
+            assert_eq!(
+                back.iter().copied().collect::<Vec<_>>(),
+                (at..10).collect::<Vec<_>>()
+            );
+            queue.append(&mut back);
+            assert_eq!(
+                queue.into_iter().rev().collect::<Vec<_>>(),
+                (0..10).rev().collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn random() {
+        let mut queue = Queue::new();
+        let mut std = VecDeque::new();
+        for _ in 0..10000 {
+            match rand::random::<u8>() % 6 {
+                0 | 1 => {
+                    let elem = rand::random::<u16>();
+                    queue.enqueue(elem);
+                    std.push_back(elem);
+                }
+                2 => assert_eq!(queue.dequeue(), std.pop_front()),
+                3 => assert_eq!(queue.pop_back(), std.pop_back()),
+                4 => {
+                    let at = rand::random::<usize>() % (std.len() + 1);
+                    let mut back = queue.split_off(at);
+                    let mut std_back = std.split_off(at);
+                    assert!(back.iter().eq(std_back.iter()));
+                    back.extend([1, 2]);
+                    std_back.extend([1, 2]);
+                    queue.append(&mut back);
+                    std.append(&mut std_back);
+                }
+                _ => {
+                    assert_eq!(queue.peek(), std.front());
+                    assert_eq!(queue.peek_back(), std.back());
+                }
+            }
+            assert_eq!(queue.len(), std.len());
+        }
+        assert!(queue.iter().rev().eq(std.iter().rev()));
+    }
 }
@@ -0,0 +1,125 @@
+use std::cell::UnsafeCell;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+
+// The parts hazard pointers and epochs share: the retired nodes waiting to
+// be freed, and a lock-free list of per-thread records to keep them in.
+
+pub(super) struct Retired {
+    pub(super) ptr: *mut (),
+    drop: unsafe fn(*mut ()),
+    // the epoch it was retired in, hazard pointers leave it at 0
+    pub(super) epoch: usize,
+}
+
+unsafe fn drop_box<T>(ptr: *mut ()) {
+    drop(Box::from_raw(ptr as *mut T));
+}
+
+impl Retired {
+    // ptr came from Box::into_raw
+    pub(super) unsafe fn new<T>(ptr: *mut T, epoch: usize) -> Self {
+        Self {
+            ptr: ptr as *mut (),
+            drop: drop_box::<T>,
+            epoch,
+        }
+    }
+
+    pub(super) unsafe fn free(&self) {
+        (self.drop)(self.ptr)
+    }
+}
+
+// One per thread using the registry at a time, never freed before the
+// registry.
+pub(super) struct Record<D> {
+    next: *mut Record<D>,
+    active: AtomicBool,
+    pub(super) data: D,
+    // only touched by the owner of the record
+    retired: UnsafeCell<Vec<Retired>>,
+}
+
+impl<D> Record<D> {
+    // the caller must hold the record
+    #[allow(clippy::mut_from_ref)]
+    pub(super) unsafe fn retired(&self) -> &mut Vec<Retired> {
+        &mut *self.retired.get()
+    }
+
+    // hand the record back, its retired nodes stay for the next owner
+    pub(super) fn release(&self) {
+        self.active.store(false, Ordering::Release);
+    }
+}
+
+pub(super) struct Registry<D> {
+    records: AtomicPtr<Record<D>>,
+}
+
+unsafe impl<D: Send + Sync> Send for Registry<D> {}
+unsafe impl<D: Send + Sync> Sync for Registry<D> {}
+
+impl<D> Registry<D> {
+    pub(super) fn new() -> Self {
+        Self {
+            records: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    // take a free record, or add one with the data from init
+    pub(super) fn acquire(&self, init: impl FnOnce() -> D) -> &Record<D> {
+        for record in self.iter() {
+            if record
+                .active
+                .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+            {
+                return record;
+            }
+        }
+
+        let record = Box::into_raw(Box::new(Record {
+            next: ptr::null_mut(),
+            active: AtomicBool::new(true),
+            data: init(),
+            retired: UnsafeCell::new(Vec::new()),
+        }));
+        let mut head = self.records.load(Ordering::Acquire);
+        loop {
+            unsafe { (*record).next = head };
+            match self
+                .records
+                .compare_exchange(head, record, Ordering::AcqRel, Ordering::Acquire)
+            {
+                Ok(_) => break,
+                Err(new) => head = new,
+            }
+        }
+        unsafe { &*record }
+    }
+
+    // every record, held or not
+    pub(super) fn iter(&self) -> impl Iterator<Item = &Record<D>> {
+        let mut record = self.records.load(Ordering::Acquire);
+        std::iter::from_fn(move || {
+            let rec = unsafe { record.as_ref()? };
+            record = rec.next;
+            Some(rec)
+        })
+    }
+}
+
+impl<D> Drop for Registry<D> {
+    fn drop(&mut self) {
+        let mut record = *self.records.get_mut();
+        while !record.is_null() {
+            let rec = unsafe { Box::from_raw(record) };
+            for retired in rec.retired.into_inner() {
+                unsafe { retired.free() };
+            }
+            record = rec.next;
+        }
+    }
+}
@@ -0,0 +1,738 @@
+use super::epoch::{Collector, Guard};
+use std::fmt;
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Bound, RangeBounds};
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+const MAX_HEIGHT: usize = 32;
+
+// 1 with probability 1/2, 2 with 1/4, ...
+fn random_height() -> usize {
+    (rand::random::<u32>() | 1 << (MAX_HEIGHT - 1)).trailing_zeros() as usize + 1
+}
+
+// whether key lies before the start of range
+fn before_start<K: Ord, R: RangeBounds<K>>(range: &R, key: &K) -> bool {
+    match range.start_bound() {
+        Bound::Included(start) => key < start,
+        Bound::Excluded(start) => key <= start,
+        Bound::Unbounded => false,
+    }
+}
+
+fn before_end<K: Ord, R: RangeBounds<K>>(range: &R, key: &K) -> bool {
+    match range.end_bound() {
+        Bound::Included(end) => key <= end,
+        Bound::Excluded(end) => key < end,
+        Bound::Unbounded => true,
+    }
+}
+
+type Link<K, V> = Option<NonNull<Node<K, V>>>;
+
+struct Node<K, V> {
+    key: K,
+    value: V,
+    // one link per level the node is in
+    next: Box<[Link<K, V>]>,
+}
+
+// Ordered map on a skip list (Pugh, 1990). Every node is in level 0 and in
+// each level above with probability 1/2, so a search skips most of the
+// nodes and takes O(log n) expected.
+pub struct SkipList<K, V> {
+    head: [Link<K, V>; MAX_HEIGHT],
+    len: usize,
+    _marker: PhantomData<Box<Node<K, V>>>,
+}
+
+unsafe impl<K: Send, V: Send> Send for SkipList<K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for SkipList<K, V> {}
+
+pub struct Iter<'a, K, V> {
+    next: Link<K, V>,
+    len: usize,
+    _marker: PhantomData<&'a Node<K, V>>,
+}
+
+pub struct Range<'a, K, V, R> {
+    next: Link<K, V>,
+    range: R,
+    _marker: PhantomData<&'a Node<K, V>>,
+}
+
+impl<K: Ord, V> SkipList<K, V> {
+    pub fn new() -> Self {
+        Self {
+            head: [None; MAX_HEIGHT],
+            len: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // for every level the link to the first node with a key >= key
+    fn search(&mut self, key: &K) -> [*mut Link<K, V>; MAX_HEIGHT] {
+        let mut links = [ptr::null_mut(); MAX_HEIGHT];
+        let mut tower: *mut [Link<K, V>] = &mut self.head[..];
+        for level in (0..MAX_HEIGHT).rev() {
+            unsafe {
+                while let Some(next) = (*tower)[level] {
+                    if (*next.as_ptr()).key >= *key {
+                        break;
+                    }
+                    tower = &mut *(*next.as_ptr()).next;
+                }
+                links[level] = &mut (*tower)[level];
+            }
+        }
+        links
+    }
+
+    // the first node whose key is not before(key)
+    fn seek(&self, before: impl Fn(&K) -> bool) -> Link<K, V> {
+        let mut tower = &self.head[..];
+        for level in (0..MAX_HEIGHT).rev() {
+            while let Some(next) = tower[level] {
+                let node = unsafe { &*next.as_ptr() };
+                if !before(&node.key) {
+                    break;
+                }
+                tower = &node.next;
+            }
+        }
+        tower[0]
+    }
+
+    fn find(&self, key: &K) -> Option<&Node<K, V>> {
+        self.seek(|k| k < key)
+            .map(|node| unsafe { &*node.as_ptr() })
+            .filter(|node| node.key == *key)
+    }
+
+    // returns the old value if the key was there already
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let links = self.search(&key);
+        unsafe {
+            if let Some(node) = *links[0] {
+                if (*node.as_ptr()).key == key {
+                    return Some(mem::replace(&mut (*node.as_ptr()).value, value));
+                }
+            }
+            let node = NonNull::from(Box::leak(Box::new(Node {
+                key,
+                value,
+                next: vec![None; random_height()].into_boxed_slice(),
+            })));
+            let tower = &mut (*node.as_ptr()).next;
+            for (level, link) in tower.iter_mut().enumerate() {
+                *link = *links[level];
+                *links[level] = Some(node);
+            }
+        }
+        self.len += 1;
+        None
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let links = self.search(key);
+        unsafe {
+            let node = (*links[0])?;
+            if (*node.as_ptr()).key != *key {
+                return None;
+            }
+            let node = Box::from_raw(node.as_ptr());
+            for (level, next) in node.next.iter().enumerate() {
+                *links[level] = *next;
+            }
+            self.len -= 1;
+            Some(node.value)
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.find(key).map(|node| &node.value)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let links = self.search(key);
+        unsafe {
+            let node = (*links[0])?;
+            if (*node.as_ptr()).key != *key {
+                return None;
+            }
+            Some(&mut (*node.as_ptr()).value)
+        }
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.find(key).is_some()
+    }
+
+    pub fn first(&self) -> Option<(&K, &V)> {
+        self.head[0].map(|node| unsafe {
+            let node = &*node.as_ptr();
+            (&node.key, &node.value)
+        })
+    }
+
+    pub fn last(&self) -> Option<(&K, &V)> {
+        let mut last = None;
+        let mut tower = &self.head[..];
+        for level in (0..MAX_HEIGHT).rev() {
+            while let Some(next) = tower[level] {
+                let node = unsafe { &*next.as_ptr() };
+                last = Some((&node.key, &node.value));
+                tower = &node.next;
+            }
+        }
+        last
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            next: self.head[0],
+            len: self.len,
+            _marker: PhantomData,
+        }
+    }
+
+    // the entries with keys in range, in order
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Range<'_, K, V, R> {
+        Range {
+            next: self.seek(|key| before_start(&range, key)),
+            range,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl<K, V> Drop for SkipList<K, V> {
+    fn drop(&mut self) {
+        let mut next = self.head[0];
+        while let Some(node) = next {
+            let node = unsafe { Box::from_raw(node.as_ptr()) };
+            next = node.next[0];
+        }
+    }
+}
+
+impl<K: Ord, V> Default for SkipList<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> Extend<(K, V)> for SkipList<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for SkipList<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<K: Ord + fmt::Debug, V: fmt::Debug> fmt::Debug for SkipList<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a SkipList<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+    fn into_iter(self) -> Iter<'a, K, V> {
+        self.iter()
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.map(|node| unsafe {
+            let node = &*node.as_ptr();
+            self.next = node.next[0];
+            self.len -= 1;
+            (&node.key, &node.value)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<'a, K: Ord, V, R: RangeBounds<K>> Iterator for Range<'a, K, V, R> {
+    type Item = (&'a K, &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = unsafe { &*self.next?.as_ptr() };
+        if !before_end(&self.range, &node.key) {
+            self.next = None;
+            return None;
+        }
+        self.next = node.next[0];
+        Some((&node.key, &node.value))
+    }
+}
+
+// the low bit of a next pointer marks its node as removed
+fn is_marked<T>(ptr: *mut T) -> bool {
+    ptr as usize & 1 == 1
+}
+
+fn marked<T>(ptr: *mut T) -> *mut T {
+    (ptr as usize | 1) as *mut T
+}
+
+fn unmarked<T>(ptr: *mut T) -> *mut T {
+    (ptr as usize & !1) as *mut T
+}
+
+struct CNode<K, V> {
+    key: K,
+    value: V,
+    // levels the node is linked in, plus one while insert still links it,
+    // the node is retired when this drops to zero
+    refs: AtomicUsize,
+    next: Box<[AtomicPtr<CNode<K, V>>]>,
+}
+
+// for each level the node to search from and the first node not before the
+// key, null stands for the head
+type Position<K, V> = (
+    [*mut CNode<K, V>; MAX_HEIGHT],
+    [*mut CNode<K, V>; MAX_HEIGHT],
+);
+
+// Lock-free skip list (Fraser, 2004; Herlihy & Shavit, 2008). A remove
+// marks the next pointers of the node from the top level down, after that
+// any search that passes the node unlinks it. Searches only go down a level
+// from a node they saw unmarked, so the nodes they reach are still in the
+// list or were removed after the search started, and are kept alive by the
+// epoch guard of the search.
+//
+// Unlike SkipList, values are cloned out instead of borrowed, and insert
+// never replaces: it returns false and keeps the old value if the key is
+// there already. The value lives in the node, a replace would have to swap
+// in a new node.
+pub struct ConcurrentSkipList<K, V> {
+    head: Box<[AtomicPtr<CNode<K, V>>]>,
+    len: AtomicUsize,
+    collector: Collector,
+}
+
+unsafe impl<K: Send + Sync, V: Send + Sync> Send for ConcurrentSkipList<K, V> {}
+unsafe impl<K: Send + Sync, V: Send + Sync> Sync for ConcurrentSkipList<K, V> {}
+
+// iterates over a snapshot of each node as it is reached, holding the guard
+pub struct ConcurrentRange<'a, K, V, R> {
+    next: *mut CNode<K, V>,
+    range: R,
+    _guard: Guard<'a>,
+}
+
+impl<K: Ord, V> ConcurrentSkipList<K, V> {
+    pub fn new() -> Self {
+        Self {
+            head: (0..MAX_HEIGHT)
+                .map(|_| AtomicPtr::new(ptr::null_mut()))
+                .collect(),
+            len: AtomicUsize::new(0),
+            collector: Collector::new(),
+        }
+    }
+
+    // only a snapshot when other threads use the list
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn tower(&self, node: *mut CNode<K, V>) -> &[AtomicPtr<CNode<K, V>>] {
+        if node.is_null() {
+            &self.head
+        } else {
+            unsafe { &(*node).next }
+        }
+    }
+
+    // a link to node is gone, retire it if it was the last one
+    fn release(&self, node: *mut CNode<K, V>, guard: &Guard<'_>) {
+        if unsafe { (*node).refs.fetch_sub(1, Ordering::AcqRel) } == 1 {
+            unsafe { guard.retire(node) };
+        }
+    }
+
+    // Walk down to the first node that is not before(key), unlinking the
+    // removed nodes on the way.
+    fn search(&self, before: impl Fn(&K) -> bool, guard: &Guard<'_>) -> Position<K, V> {
+        'retry: loop {
+            let mut preds = [ptr::null_mut(); MAX_HEIGHT];
+            let mut succs = [ptr::null_mut(); MAX_HEIGHT];
+            let mut pred = ptr::null_mut();
+            for level in (0..MAX_HEIGHT).rev() {
+                let mut curr = self.tower(pred)[level].load(Ordering::Acquire);
+                if is_marked(curr) {
+                    continue 'retry;
+                }
+                while !curr.is_null() {
+                    let node = unsafe { &*curr };
+                    let succ = node.next[level].load(Ordering::Acquire);
+                    if is_marked(succ) {
+                        match self.tower(pred)[level].compare_exchange(
+                            curr,
+                            unmarked(succ),
+                            Ordering::AcqRel,
+                            Ordering::Acquire,
+                        ) {
+                            Ok(_) => {
+                                self.release(curr, guard);
+                                curr = unmarked(succ);
+                                continue;
+                            }
+                            Err(_) => continue 'retry,
+                        }
+                    }
+                    if !before(&node.key) {
+                        break;
+                    }
+                    pred = curr;
+                    curr = succ;
+                }
+                preds[level] = pred;
+                succs[level] = curr;
+            }
+            return (preds, succs);
+        }
+    }
+
+    // the node with key, if it is in the list
+    fn find(&self, key: &K, guard: &Guard<'_>) -> Option<&CNode<K, V>> {
+        let (_, succs) = self.search(|k| k < key, guard);
+        unsafe { succs[0].as_ref() }.filter(|node| node.key == *key)
+    }
+
+    // returns false and leaves the list as it is if the key is there already
+    pub fn insert(&self, key: K, value: V) -> bool {
+        let guard = self.collector.pin();
+        let height = random_height();
+        let node = Box::into_raw(Box::new(CNode {
+            key,
+            value,
+            refs: AtomicUsize::new(2),
+            next: (0..height)
+                .map(|_| AtomicPtr::new(ptr::null_mut()))
+                .collect(),
+        }));
+        let n = unsafe { &*node };
+
+        let (mut preds, mut succs) = loop {
+            let (preds, succs) = self.search(|k| *k < n.key, &guard);
+            if unsafe { succs[0].as_ref() }.is_some_and(|succ| succ.key == n.key) {
+                drop(unsafe { Box::from_raw(node) });
+                return false;
+            }
+            n.next[0].store(succs[0], Ordering::Relaxed);
+            if self.tower(preds[0])[0]
+                .compare_exchange(succs[0], node, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                break (preds, succs);
+            }
+        };
+        self.len.fetch_add(1, Ordering::Relaxed);
+
+        // link the levels above, bottom up, until a remove gets in the way
+        'link: for level in 1..height {
+            loop {
+                let next = n.next[level].load(Ordering::Acquire);
+                if is_marked(next) {
+                    break 'link;
+                }
+                if next != succs[level]
+                    && n.next[level]
+                        .compare_exchange(next, succs[level], Ordering::AcqRel, Ordering::Acquire)
+                        .is_err()
+                {
+                    continue;
+                }
+                n.refs.fetch_add(1, Ordering::Relaxed);
+                if self.tower(preds[level])[level]
+                    .compare_exchange(succs[level], node, Ordering::AcqRel, Ordering::Acquire)
+                    .is_ok()
+                {
+                    break;
+                }
+                n.refs.fetch_sub(1, Ordering::Relaxed);
+                let (new_preds, new_succs) = self.search(|k| *k < n.key, &guard);
+                if new_succs[0] != node {
+                    break 'link;
+                }
+                preds = new_preds;
+                succs = new_succs;
+            }
+        }
+        // a remove may have missed the levels linked after it marked them
+        if is_marked(n.next[0].load(Ordering::Acquire)) {
+            self.search(|k| *k < n.key, &guard);
+        }
+        self.release(node, &guard);
+        true
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        let guard = self.collector.pin();
+        self.find(key, &guard).is_some()
+    }
+
+    // iterates over the entries with keys in range, in order
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> ConcurrentRange<'_, K, V, R> {
+        let guard = self.collector.pin();
+        let (_, succs) = self.search(|key| before_start(&range, key), &guard);
+        ConcurrentRange {
+            next: succs[0],
+            range,
+            _guard: guard,
+        }
+    }
+
+    pub fn iter(&self) -> ConcurrentRange<'_, K, V, std::ops::RangeFull> {
+        self.range(..)
+    }
+}
+
+impl<K: Ord, V: Clone> ConcurrentSkipList<K, V> {
+    pub fn get(&self, key: &K) -> Option<V> {
+        let guard = self.collector.pin();
+        self.find(key, &guard).map(|node| node.value.clone())
+    }
+
+    pub fn remove(&self, key: &K) -> Option<V> {
+        let guard = self.collector.pin();
+        let node = self.find(key, &guard)?;
+        for level in (1..node.next.len()).rev() {
+            let next = &node.next[level];
+            let mut ptr = next.load(Ordering::Acquire);
+            while !is_marked(ptr) {
+                match next.compare_exchange(ptr, marked(ptr), Ordering::AcqRel, Ordering::Acquire) {
+                    Ok(_) => break,
+                    Err(now) => ptr = now,
+                }
+            }
+        }
+        // whoever marks level 0 removed the node
+        let mut ptr = node.next[0].load(Ordering::Acquire);
+        loop {
+            if is_marked(ptr) {
+                return None;
+            }
+            match node.next[0].compare_exchange(
+                ptr,
+                marked(ptr),
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => break,
+                Err(now) => ptr = now,
+            }
+        }
+        let value = node.value.clone();
+        self.len.fetch_sub(1, Ordering::Relaxed);
+        self.search(|k| k < key, &guard);
+        Some(value)
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> ConcurrentSkipList<K, V> {
+    pub fn first(&self) -> Option<(K, V)> {
+        self.iter().next()
+    }
+
+    pub fn last(&self) -> Option<(K, V)> {
+        let guard = self.collector.pin();
+        let (preds, _) = self.search(|_| true, &guard);
+        unsafe { preds[0].as_ref() }.map(|node| (node.key.clone(), node.value.clone()))
+    }
+}
+
+impl<K, V> Drop for ConcurrentSkipList<K, V> {
+    fn drop(&mut self) {
+        let mut next = *self.head[0].get_mut();
+        while !next.is_null() {
+            let mut node = unsafe { Box::from_raw(unmarked(next)) };
+            next = *node.next[0].get_mut();
+        }
+    }
+}
+
+impl<K: Ord, V> Default for ConcurrentSkipList<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord + Clone, V: Clone, R: RangeBounds<K>> Iterator for ConcurrentRange<'_, K, V, R> {
+    type Item = (K, V);
+    fn next(&mut self) -> Option<(K, V)> {
+        while let Some(node) = unsafe { unmarked(self.next).as_ref() } {
+            let next = node.next[0].load(Ordering::Acquire);
+            if !before_end(&self.range, &node.key) {
+                break;
+            }
+            self.next = next;
+            // skip the removed nodes
+            if !is_marked(next) {
+                return Some((node.key.clone(), node.value.clone()));
+            }
+        }
+        self.next = ptr::null_mut();
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ConcurrentSkipList, SkipList};
+    use std::collections::BTreeMap;
+    use std::thread;
+
+    #[test]
+    fn basic() {
+        let mut list = SkipList::new();
+        assert_eq!(list.first(), None);
+        assert_eq!(list.last(), None);
+        for key in [5, 1, 9, 3, 7] {
+            assert_eq!(list.insert(key, key * 10), None);
+        }
+        assert_eq!(list.insert(3, 33), Some(30));
+        assert_eq!(list.len(), 5);
+        assert_eq!(list.get(&3), Some(&33));
+        *list.get_mut(&9).unwrap() += 1;
+        assert_eq!(list.first(), Some((&1, &10)));
+        assert_eq!(list.last(), Some((&9, &91)));
+        assert_eq!(format!("{:?}", list), "{1: 10, 3: 33, 5: 50, 7: 70, 9: 91}");
+        assert_eq!(
+            list.range(2..7).map(|(k, _)| *k).collect::<Vec<_>>(),
+            vec![3, 5]
+        );
+        assert_eq!(list.range(3..=7).count(), 3);
+        assert_eq!(list.remove(&5), Some(50));
+        assert_eq!(list.remove(&5), None);
+        assert!(!list.contains_key(&5));
+        list.clear();
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn random() {
+        let mut list = SkipList::new();
+        let mut std = BTreeMap::new();
+        for _ in 0..20000 {
+            let key = rand::random::<u16>() % 1000;
+            match rand::random::<u8>() % 4 {
+                0 | 1 => assert_eq!(list.insert(key, key), std.insert(key, key)),
+                2 => assert_eq!(list.remove(&key), std.remove(&key)),
+                _ => {
+                    let end = key + rand::random::<u16>() % 100;
+                    assert!(list.range(key..end).eq(std.range(key..end)));
+                }
+            }
+            assert_eq!(list.len(), std.len());
+            assert_eq!(list.first(), std.iter().next());
+            assert_eq!(list.last(), std.iter().next_back());
+        }
+        assert!(list.iter().eq(std.iter()));
+        assert_eq!(list.iter().len(), std.len());
+    }
+
+    #[test]
+    fn concurrent() {
+        const THREADS: u32 = 4;
+        const KEYS: u32 = 5000;
+
+        let list = ConcurrentSkipList::new();
+        thread::scope(|s| {
+            for t in 0..THREADS {
+                let list = &list;
+                s.spawn(move || {
+                    // every thread owns the keys equal to t mod THREADS
+                    for i in 0..KEYS {
+                        let key = i * THREADS + t;
+                        assert!(list.insert(key, key));
+                        assert!(!list.insert(key, 0));
+                        if i % 2 == 1 {
+                            assert_eq!(list.remove(&key), Some(key));
+                            assert_eq!(list.get(&key), None);
+                        }
+                    }
+                });
+            }
+            // readers see ordered snapshots while the list changes
+            s.spawn(|| {
+                for _ in 0..100 {
+                    let keys: Vec<_> = list.range(1000..5000).map(|(k, _)| k).collect();
+                    assert!(keys.windows(2).all(|w| w[0] < w[1]));
+                    assert!(keys.iter().all(|k| (1000..5000).contains(k)));
+                }
+            });
+        });
+        let keys: Vec<_> = list.iter().map(|(k, _)| k).collect();
+        let expected: Vec<_> = (0..KEYS * THREADS)
+            .filter(|key| key / THREADS % 2 == 0)
+            .collect();
+        assert_eq!(keys, expected);
+        assert_eq!(list.len(), expected.len());
+        assert_eq!(list.first(), Some((0, 0)));
+        let last = *expected.last().unwrap();
+        assert_eq!(list.last(), Some((last, last)));
+    }
+
+    #[test]
+    fn concurrent_contended() {
+        // all threads fight over the same few keys
+        let list = ConcurrentSkipList::new();
+        thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..20000 {
+                        let key = rand::random::<u8>() % 32;
+                        if rand::random() {
+                            list.insert(key, key);
+                        } else if let Some(value) = list.remove(&key) {
+                            assert_eq!(value, key);
+                        }
+                    }
+                });
+            }
+        });
+        let keys: Vec<_> = list.iter().map(|(k, _)| k).collect();
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(keys.len(), list.len());
+    }
+}
@@ -1,19 +1,34 @@
-use std::rc::Rc;
+use crate::ptr_kind::{ArcK, PointerKind, RcK};
+use std::fmt;
+use std::iter::FromIterator;
 
-struct List<T> {
-    head: Option<Rc<Node<T>>>,
+// Persistent stack, push and pop return a new list sharing the nodes with
+// the old one. SyncList can be shared between threads.
+pub struct List<T, P: PointerKind = RcK> {
+    head: Link<T, P>,
+    len: usize,
 }
 
-struct Node<T> {
+pub type SyncList<T> = List<T, ArcK>;
+
+type Link<T, P> = Option<<P as PointerKind>::Ptr<Node<T, P>>>;
+
+struct Node<T, P: PointerKind> {
     val: T,
-    next: Option<Rc<Node<T>>>,
+    next: Link<T, P>,
+}
+
+pub struct Iter<'a, T, P: PointerKind + 'a> {
+    next: Option<&'a Node<T, P>>,
+    len: usize,
 }
 
-impl<T> Drop for List<T> {
+impl<T, P: PointerKind> Drop for List<T, P> {
+    // stop at the first node that is shared with another list
     fn drop(&mut self) {
         let mut head = self.head.take();
         while let Some(node) = head {
-            if let Ok(mut node) = Rc::try_unwrap(node) {
+            if let Some(mut node) = P::into_inner(node) {
                 head = node.next.take();
             } else {
                 break;
@@ -22,37 +37,144 @@ impl<T> Drop for List<T> {
     }
 }
 
-impl<T> List<T> {
-    fn new() -> List<T> {
-        List { head: None }
+impl<T, P: PointerKind> List<T, P> {
+    pub fn new() -> Self {
+        List { head: None, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.is_none()
     }
 
-    fn pop(&self) -> List<T> {
+    pub fn pop(&self) -> Self {
         List {
             head: self.head.as_ref().and_then(|node| node.next.clone()),
+            len: self.len.saturating_sub(1),
         }
     }
 
-    fn push(&self, val: T) -> List<T> {
+    pub fn push(&self, val: T) -> Self {
         List {
-            head: Some(Rc::new(Node {
+            head: Some(P::new(Node {
                 val,
                 next: self.head.clone(),
             })),
+            len: self.len + 1,
         }
     }
 
-    fn peek(&self) -> Option<&T> {
+    pub fn peek(&self) -> Option<&T> {
         self.head.as_ref().map(|node| &node.val)
     }
+
+    // from the top of the stack down
+    pub fn iter(&self) -> Iter<'_, T, P> {
+        Iter {
+            next: self.head.as_deref(),
+            len: self.len,
+        }
+    }
+
+    pub fn fold<B, F: FnMut(B, &T) -> B>(&self, init: B, f: F) -> B {
+        self.iter().fold(init, f)
+    }
+
+    // a new list of the same shape, nothing is shared with self
+    pub fn map<U, F: FnMut(&T) -> U>(&self, f: F) -> List<U, P> {
+        self.iter().map(f).collect()
+    }
 }
 
+impl<T: Clone, P: PointerKind> List<T, P> {
+    pub fn reverse(&self) -> Self {
+        self.fold(Self::new(), |list, val| list.push(val.clone()))
+    }
+
+    // self on top of other, the nodes of other are shared
+    pub fn append(&self, other: &Self) -> Self {
+        let vals: Vec<&T> = self.iter().collect();
+        vals.into_iter()
+            .rev()
+            .fold(other.clone(), |list, val| list.push(val.clone()))
+    }
+}
+
+impl<T, P: PointerKind> Clone for List<T, P> {
+    fn clone(&self) -> Self {
+        List {
+            head: self.head.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<T, P: PointerKind> Default for List<T, P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// the first element ends up on top, so iter() gives them back in order
+impl<T, P: PointerKind> FromIterator<T> for List<T, P> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let vals: Vec<T> = iter.into_iter().collect();
+        vals.into_iter()
+            .rev()
+            .fold(Self::new(), |list, val| list.push(val))
+    }
+}
+
+impl<T: PartialEq, P: PointerKind> PartialEq for List<T, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq, P: PointerKind> Eq for List<T, P> {}
+
+impl<T: fmt::Debug, P: PointerKind> fmt::Debug for List<T, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<'a, T, P: PointerKind> IntoIterator for &'a List<T, P> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, P>;
+    fn into_iter(self) -> Iter<'a, T, P> {
+        self.iter()
+    }
+}
+
+impl<'a, T, P: PointerKind> Iterator for Iter<'a, T, P> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<&'a T> {
+        self.next.map(|node| {
+            self.next = node.next.as_deref();
+            self.len -= 1;
+            &node.val
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T, P: PointerKind> ExactSizeIterator for Iter<'_, T, P> {}
+
 #[cfg(test)]
 mod test {
-    use super::List;
+    use super::{List, SyncList};
+    use std::thread;
+
     #[test]
     fn test() {
-        let list = List::new();
+        let list: List<_> = List::new();
         assert_eq!(list.peek(), None);
 
         let list = list.push(10);
@@ -64,4 +186,58 @@ mod test {
         let list = list.pop();
         assert_eq!(list.peek(), Some(&10));
     }
+
+    #[test]
+    fn bulk() {
+        let list: List<_> = (0..5).collect();
+        assert_eq!(list.len(), 5);
+        assert_eq!(list.peek(), Some(&0));
+        assert_eq!(
+            list.iter().copied().collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 4]
+        );
+        assert_eq!(list.reverse(), (0..5).rev().collect());
+        assert_eq!(list.fold(0, |sum, val| sum + val), 10);
+        assert_eq!(
+            list.map(|val| val * 2),
+            [0, 2, 4, 6, 8].iter().copied().collect()
+        );
+
+        let top = list.pop().pop();
+        let both = top.append(&list);
+        assert_eq!(both.len(), 8);
+        assert_eq!(format!("{:?}", both), "[2, 3, 4, 0, 1, 2, 3, 4]");
+        assert_eq!(both.pop().pop().pop(), list);
+        assert_eq!(List::new().append(&list), list);
+        assert!(List::<i32>::new().pop().is_empty());
+
+        // long lists with shared tails drop without recursing
+        let long: List<_> = (0..1_000_000).collect();
+        let short = long.pop().push(1);
+        drop(long);
+        assert_eq!(short.len(), 1_000_000);
+    }
+
+    #[test]
+    fn sync() {
+        let history: SyncList<_> = (0..100).collect();
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let history = history.clone();
+                thread::spawn(move || {
+                    let mine = history.pop().push(i);
+                    assert_eq!(
+                        mine.iter().skip(1).copied().collect::<Vec<_>>(),
+                        (1..100).collect::<Vec<_>>()
+                    );
+                    mine
+                })
+            })
+            .collect();
+        for (i, handle) in handles.into_iter().enumerate() {
+            let mine = handle.join().unwrap();
+            assert_eq!(mine.peek(), Some(&i));
+        }
+        assert_eq!(history.len(), 100);
+    }
 }
@@ -0,0 +1,53 @@
+use std::ops::Deref;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Shared pointer for the nodes of the persistent structures, RcK for one
+/// thread, ArcK to share versions across threads.
+pub trait PointerKind {
+    type Ptr<T>: Deref<Target = T> + Clone;
+
+    fn new<T>(val: T) -> Self::Ptr<T>;
+
+    // clone the value first if it is shared
+    fn make_mut<T: Clone>(ptr: &mut Self::Ptr<T>) -> &mut T;
+
+    // the value if ptr was the last pointer to it
+    fn into_inner<T>(ptr: Self::Ptr<T>) -> Option<T>;
+}
+
+pub struct RcK;
+
+impl PointerKind for RcK {
+    type Ptr<T> = Rc<T>;
+
+    fn new<T>(val: T) -> Rc<T> {
+        Rc::new(val)
+    }
+
+    fn make_mut<T: Clone>(ptr: &mut Rc<T>) -> &mut T {
+        Rc::make_mut(ptr)
+    }
+
+    fn into_inner<T>(ptr: Rc<T>) -> Option<T> {
+        Rc::into_inner(ptr)
+    }
+}
+
+pub struct ArcK;
+
+impl PointerKind for ArcK {
+    type Ptr<T> = Arc<T>;
+
+    fn new<T>(val: T) -> Arc<T> {
+        Arc::new(val)
+    }
+
+    fn make_mut<T: Clone>(ptr: &mut Arc<T>) -> &mut T {
+        Arc::make_mut(ptr)
+    }
+
+    fn into_inner<T>(ptr: Arc<T>) -> Option<T> {
+        Arc::into_inner(ptr)
+    }
+}
@@ -0,0 +1,388 @@
+use std::alloc::{self, Layout};
+use std::cell::{Cell, RefCell};
+use std::cmp;
+use std::ptr;
+
+/// Allocator used by RawVec, it has the same shape as std::alloc::GlobalAlloc:
+/// a null pointer means the allocation failed.
+///
+/// # Safety
+///
+/// Memory returned by alloc/realloc must be valid for the given layout
+/// until it is passed to dealloc/realloc, or until the allocator is dropped.
+pub unsafe trait Allocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8;
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout);
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        let new_ptr = self.alloc(new_layout);
+        if !new_ptr.is_null() {
+            ptr::copy_nonoverlapping(ptr, new_ptr, cmp::min(layout.size(), new_size));
+            self.dealloc(ptr, layout);
+        }
+        new_ptr
+    }
+}
+
+unsafe impl<A: Allocator + ?Sized> Allocator for &A {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        (**self).alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        (**self).dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        (**self).realloc(ptr, layout, new_size)
+    }
+}
+
+// the global allocator, i.e. whatever #[global_allocator] is
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Global;
+
+unsafe impl Allocator for Global {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        alloc::alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        alloc::dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        alloc::realloc(ptr, layout, new_size)
+    }
+}
+
+const CHUNK_SIZE: usize = 4096;
+const CHUNK_ALIGN: usize = 16;
+
+// Arena allocator, memory is only given back when the arena is dropped.
+// Use it by reference: Vec::new_in(&bump).
+pub struct BumpAlloc {
+    chunks: RefCell<std::vec::Vec<(*mut u8, Layout)>>,
+    // [ptr, end) is the free space of the last chunk
+    ptr: Cell<usize>,
+    end: Cell<usize>,
+}
+
+impl BumpAlloc {
+    pub fn new() -> Self {
+        Self {
+            chunks: RefCell::new(std::vec::Vec::new()),
+            ptr: Cell::new(0),
+            end: Cell::new(0),
+        }
+    }
+
+    // total bytes taken from the global allocator
+    pub fn chunk_bytes(&self) -> usize {
+        self.chunks
+            .borrow()
+            .iter()
+            .map(|(_, layout)| layout.size())
+            .sum()
+    }
+
+    fn new_chunk(&self, layout: Layout) -> bool {
+        let last = self.chunks.borrow().last().map_or(0, |(_, l)| l.size());
+        let size = cmp::max(
+            cmp::max(CHUNK_SIZE, last.saturating_mul(2)),
+            layout.size().saturating_add(layout.align()),
+        );
+        let chunk_layout = match Layout::from_size_align(size, CHUNK_ALIGN) {
+            Ok(layout) => layout,
+            Err(_) => return false,
+        };
+
+        let chunk = unsafe { alloc::alloc(chunk_layout) };
+        if chunk.is_null() {
+            return false;
+        }
+        self.chunks.borrow_mut().push((chunk, chunk_layout));
+        self.ptr.set(chunk as usize);
+        self.end.set(chunk as usize + size);
+        true
+    }
+
+    fn bump(&self, layout: Layout) -> Option<*mut u8> {
+        if self.end.get() == 0 {
+            return None;
+        }
+        let start = self.ptr.get().checked_add(layout.align() - 1)? & !(layout.align() - 1);
+        let end = start.checked_add(layout.size())?;
+        if end > self.end.get() {
+            return None;
+        }
+        self.ptr.set(end);
+        Some(start as *mut u8)
+    }
+
+    // only the last allocation can grow, shrink or be freed in place
+    fn is_last(&self, ptr: *mut u8, layout: Layout) -> bool {
+        ptr as usize + layout.size() == self.ptr.get()
+    }
+}
+
+impl Default for BumpAlloc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl Allocator for BumpAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if let Some(ptr) = self.bump(layout) {
+            return ptr;
+        }
+        if !self.new_chunk(layout) {
+            return ptr::null_mut();
+        }
+        self.bump(layout).unwrap()
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if self.is_last(ptr, layout) {
+            self.ptr.set(ptr as usize);
+        }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if self.is_last(ptr, layout) && ptr as usize + new_size <= self.end.get() {
+            self.ptr.set(ptr as usize + new_size);
+            return ptr;
+        }
+        if new_size <= layout.size() {
+            return ptr;
+        }
+
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        let new_ptr = self.alloc(new_layout);
+        if !new_ptr.is_null() {
+            ptr::copy_nonoverlapping(ptr, new_ptr, cmp::min(layout.size(), new_size));
+        }
+        new_ptr
+    }
+}
+
+impl Drop for BumpAlloc {
+    fn drop(&mut self) {
+        for (chunk, layout) in self.chunks.get_mut().drain(..) {
+            unsafe {
+                alloc::dealloc(chunk, layout);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AllocStats {
+    pub allocs: usize,
+    pub deallocs: usize,
+    pub reallocs: usize,
+    // bytes currently allocated
+    pub bytes: usize,
+    pub peak_bytes: usize,
+}
+
+// Wrap another allocator and count every call made through it.
+// Use it by reference so the numbers can be read while the vector is alive.
+#[derive(Default)]
+pub struct CountingAlloc<A: Allocator = Global> {
+    inner: A,
+    stats: Cell<AllocStats>,
+}
+
+impl CountingAlloc {
+    pub fn new() -> Self {
+        Self::new_in(Global)
+    }
+}
+
+impl<A: Allocator> CountingAlloc<A> {
+    pub fn new_in(inner: A) -> Self {
+        Self {
+            inner,
+            stats: Cell::new(AllocStats::default()),
+        }
+    }
+
+    pub fn stats(&self) -> AllocStats {
+        self.stats.get()
+    }
+
+    pub fn reset(&self) {
+        self.stats.set(AllocStats::default());
+    }
+
+    fn update(&self, f: impl FnOnce(&mut AllocStats)) {
+        let mut stats = self.stats.get();
+        f(&mut stats);
+        stats.peak_bytes = cmp::max(stats.peak_bytes, stats.bytes);
+        self.stats.set(stats);
+    }
+}
+
+unsafe impl<A: Allocator> Allocator for CountingAlloc<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if !ptr.is_null() {
+            self.update(|stats| {
+                stats.allocs += 1;
+                stats.bytes += layout.size();
+            });
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout);
+        self.update(|stats| {
+            stats.deallocs += 1;
+            stats.bytes -= layout.size();
+        });
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.inner.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            self.update(|stats| {
+                stats.reallocs += 1;
+                stats.bytes = stats.bytes - layout.size() + new_size;
+            });
+        }
+        new_ptr
+    }
+}
+
+// Raise the alignment of every allocation to at least ALIGN bytes, e.g. for
+// SIMD loads, zero-copy archives or O_DIRECT buffers.
+#[derive(Clone, Copy, Debug)]
+pub struct Aligned<A: Allocator, const ALIGN: usize> {
+    inner: A,
+}
+
+impl<const ALIGN: usize> Aligned<Global, ALIGN> {
+    pub fn new() -> Self {
+        Self::new_in(Global)
+    }
+}
+
+impl<A: Allocator, const ALIGN: usize> Aligned<A, ALIGN> {
+    const VALID: () = assert!(ALIGN.is_power_of_two(), "ALIGN must be a power of two");
+
+    pub fn new_in(inner: A) -> Self {
+        let () = Self::VALID;
+        Self { inner }
+    }
+
+    fn layout(layout: Layout) -> Option<Layout> {
+        layout.align_to(ALIGN).ok()
+    }
+}
+
+// not derived, so the ALIGN check in new_in runs for defaults too
+impl<A: Allocator + Default, const ALIGN: usize> Default for Aligned<A, ALIGN> {
+    fn default() -> Self {
+        Self::new_in(A::default())
+    }
+}
+
+unsafe impl<A: Allocator, const ALIGN: usize> Allocator for Aligned<A, ALIGN> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        match Self::layout(layout) {
+            Some(layout) => self.inner.alloc(layout),
+            None => ptr::null_mut(),
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, Self::layout(layout).unwrap())
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let layout = Self::layout(layout).unwrap();
+        // the new size must still be valid with the raised alignment
+        if Layout::from_size_align(new_size, layout.align()).is_err() {
+            return ptr::null_mut();
+        }
+        self.inner.realloc(ptr, layout, new_size)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bump() {
+        let bump = BumpAlloc::new();
+        let layout = Layout::from_size_align(24, 8).unwrap();
+        unsafe {
+            let a = bump.alloc(layout);
+            let b = bump.alloc(layout);
+            assert_eq!(a as usize % 8, 0);
+            assert_eq!(b as usize, a as usize + 24);
+
+            // the last allocation grows in place, others are copied
+            *a = 1;
+            assert_eq!(bump.realloc(b, layout, 100), b);
+            let c = bump.realloc(a, layout, 100);
+            assert_ne!(c, a);
+            assert_eq!(*c, 1);
+
+            // larger than a chunk
+            let big = Layout::from_size_align(CHUNK_SIZE * 4, 64).unwrap();
+            let d = bump.alloc(big);
+            assert_eq!(d as usize % 64, 0);
+            bump.dealloc(d, big);
+            assert_eq!(bump.alloc(big), d);
+        }
+        assert!(bump.chunk_bytes() >= CHUNK_SIZE * 5);
+    }
+
+    #[test]
+    fn counting() {
+        let counting = CountingAlloc::new();
+        let layout = Layout::from_size_align(64, 8).unwrap();
+        unsafe {
+            let a = counting.alloc(layout);
+            let b = counting.realloc(a, layout, 256);
+            let layout = Layout::from_size_align(256, 8).unwrap();
+            let c = counting.alloc(layout);
+            counting.dealloc(b, layout);
+            counting.dealloc(c, layout);
+        }
+        assert_eq!(
+            counting.stats(),
+            AllocStats {
+                allocs: 2,
+                deallocs: 2,
+                reallocs: 1,
+                bytes: 0,
+                peak_bytes: 512,
+            }
+        );
+    }
+
+    #[test]
+    fn aligned() {
+        let aligned = Aligned::<_, 4096>::new_in(CountingAlloc::new());
+        let layout = Layout::from_size_align(100, 1).unwrap();
+        unsafe {
+            let a = aligned.alloc(layout);
+            assert_eq!(a as usize % 4096, 0);
+            let b = aligned.realloc(a, layout, 10000);
+            assert_eq!(b as usize % 4096, 0);
+            aligned.dealloc(b, Layout::from_size_align(10000, 1).unwrap());
+
+            let huge = Layout::from_size_align(isize::MAX as usize - 100, 1).unwrap();
+            assert!(aligned.alloc(huge).is_null());
+        }
+        assert_eq!(aligned.inner.stats().bytes, 0);
+    }
+}
@@ -0,0 +1,341 @@
+use super::Vec;
+use std::ops;
+
+// Handle of an element in the Arena. The generation of a slot is bumped every
+// time it is freed, so a handle to a removed element never matches again even
+// after the slot is reused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Index {
+    slot: usize,
+    generation: u64,
+}
+
+enum Entry<T> {
+    Occupied {
+        generation: u64,
+        val: T,
+    },
+    Free {
+        generation: u64,
+        next: Option<usize>,
+    },
+}
+
+impl<T> Entry<T> {
+    fn get(&self, idx: Index) -> Option<&T> {
+        match self {
+            Entry::Occupied { generation, val } if *generation == idx.generation => Some(val),
+            _ => None,
+        }
+    }
+
+    fn get_mut(&mut self, idx: Index) -> Option<&mut T> {
+        match self {
+            Entry::Occupied { generation, val } if *generation == idx.generation => Some(val),
+            _ => None,
+        }
+    }
+}
+
+// Slab with stable handles, insert and remove are O(1) with a free list.
+pub struct Arena<T> {
+    entries: Vec<Entry<T>>,
+    free: Option<usize>,
+    len: usize,
+}
+
+pub type Slab<T> = Arena<T>;
+
+impl<T> Arena<T> {
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    pub fn with_capacity(cap: usize) -> Self {
+        Self {
+            entries: Vec::with_capacity(cap),
+            free: None,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.entries.capacity()
+    }
+
+    pub fn insert(&mut self, val: T) -> Index {
+        self.len += 1;
+        match self.free {
+            Some(slot) => {
+                let entry = &mut self.entries[slot];
+                let generation = match *entry {
+                    Entry::Free { generation, next } => {
+                        self.free = next;
+                        generation
+                    }
+                    Entry::Occupied { .. } => unreachable!("occupied entry in free list"),
+                };
+                *entry = Entry::Occupied { generation, val };
+                Index { slot, generation }
+            }
+            None => {
+                let slot = self.entries.len();
+                self.entries.push(Entry::Occupied { generation: 0, val });
+                Index {
+                    slot,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    pub fn remove(&mut self, idx: Index) -> Option<T> {
+        if !self.contains(idx) {
+            return None;
+        }
+
+        let free = Entry::Free {
+            generation: idx.generation + 1,
+            next: self.free,
+        };
+        self.free = Some(idx.slot);
+        self.len -= 1;
+        match std::mem::replace(&mut self.entries[idx.slot], free) {
+            Entry::Occupied { val, .. } => Some(val),
+            Entry::Free { .. } => unreachable!(),
+        }
+    }
+
+    pub fn contains(&self, idx: Index) -> bool {
+        self.get(idx).is_some()
+    }
+
+    pub fn get(&self, idx: Index) -> Option<&T> {
+        self.entries.get(idx.slot)?.get(idx)
+    }
+
+    pub fn get_mut(&mut self, idx: Index) -> Option<&mut T> {
+        self.entries.get_mut(idx.slot)?.get_mut(idx)
+    }
+
+    // borrow two different elements at once, e.g. to link two nodes
+    pub fn get2_mut(&mut self, a: Index, b: Index) -> (Option<&mut T>, Option<&mut T>) {
+        assert_ne!(a.slot, b.slot, "get2_mut with the same slot");
+        let (lo, hi) = if a.slot < b.slot { (a, b) } else { (b, a) };
+        if hi.slot >= self.entries.len() {
+            return if a.slot < b.slot {
+                (self.get_mut(a), None)
+            } else {
+                (None, self.get_mut(b))
+            };
+        }
+
+        let (head, tail) = self.entries.split_at_mut(hi.slot);
+        let (lo, hi) = (head[lo.slot].get_mut(lo), tail[0].get_mut(hi));
+        if a.slot < b.slot {
+            (lo, hi)
+        } else {
+            (hi, lo)
+        }
+    }
+
+    pub fn clear(&mut self) {
+        // free every slot so old handles stay invalid
+        let mut free = None;
+        for (slot, entry) in self.entries.iter_mut().enumerate().rev() {
+            let generation = match entry {
+                Entry::Occupied { generation, .. } => *generation + 1,
+                Entry::Free { generation, .. } => *generation,
+            };
+            *entry = Entry::Free {
+                generation,
+                next: free,
+            };
+            free = Some(slot);
+        }
+        self.free = free;
+        self.len = 0;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Index, &T)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, entry)| match entry {
+                Entry::Occupied { generation, val } => Some((
+                    Index {
+                        slot,
+                        generation: *generation,
+                    },
+                    val,
+                )),
+                Entry::Free { .. } => None,
+            })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Index, &mut T)> {
+        self.entries
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(slot, entry)| match entry {
+                Entry::Occupied { generation, val } => Some((
+                    Index {
+                        slot,
+                        generation: *generation,
+                    },
+                    val,
+                )),
+                Entry::Free { .. } => None,
+            })
+    }
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> ops::Index<Index> for Arena<T> {
+    type Output = T;
+    fn index(&self, idx: Index) -> &T {
+        self.get(idx).expect("invalid arena index")
+    }
+}
+
+impl<T> ops::IndexMut<Index> for Arena<T> {
+    fn index_mut(&mut self, idx: Index) -> &mut T {
+        self.get_mut(idx).expect("invalid arena index")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Arena, Index};
+    use rand::random;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test() {
+        let mut arena = Arena::new();
+        let mut map = HashMap::new();
+        let mut removed = std::vec::Vec::new();
+
+        for _ in 0..10000 {
+            if random::<u32>() % 3 != 0 || map.is_empty() {
+                let val = random::<i32>();
+                let idx = arena.insert(val);
+                assert!(map.insert(idx, val).is_none());
+            } else {
+                let idx = *map.keys().nth(random::<usize>() % map.len()).unwrap();
+                assert_eq!(arena.remove(idx), map.remove(&idx));
+                removed.push(idx);
+            }
+            assert_eq!(arena.len(), map.len());
+        }
+
+        for (idx, val) in &map {
+            assert_eq!(arena[*idx], *val);
+        }
+        for idx in &removed {
+            if !map.contains_key(idx) {
+                assert_eq!(arena.get(*idx), None);
+                assert_eq!(arena.remove(*idx), None);
+            }
+        }
+        assert_eq!(arena.iter().count(), map.len());
+        for (idx, val) in arena.iter_mut() {
+            *val = map[&idx].wrapping_add(1);
+        }
+        assert!(arena
+            .iter()
+            .all(|(idx, val)| *val == map[&idx].wrapping_add(1)));
+
+        let cap = arena.capacity();
+        arena.clear();
+        assert!(map.keys().all(|idx| !arena.contains(*idx)));
+        for _ in 0..cap {
+            arena.insert(0);
+        }
+        assert_eq!(arena.capacity(), cap);
+    }
+
+    #[test]
+    fn stale() {
+        let mut arena = Arena::new();
+        let a = arena.insert("a");
+        assert_eq!(arena.remove(a), Some("a"));
+
+        // same slot, new generation
+        let b = arena.insert("b");
+        assert_ne!(a, b);
+        assert_eq!(arena.get(a), None);
+        assert_eq!(arena.get(b), Some(&"b"));
+        assert_eq!(arena.remove(a), None);
+        assert_eq!(arena.len(), 1);
+    }
+
+    // doubly-linked list without unsafe or Rc
+    struct Node {
+        val: i32,
+        prev: Option<Index>,
+        next: Option<Index>,
+    }
+
+    #[test]
+    fn linked_list() {
+        let mut arena = Arena::new();
+        let mut head = None;
+        let mut tail: Option<Index> = None;
+        for val in 0..10 {
+            let node = arena.insert(Node {
+                val,
+                prev: tail,
+                next: None,
+            });
+            match tail {
+                Some(tail) => arena[tail].next = Some(node),
+                None => head = Some(node),
+            }
+            tail = Some(node);
+        }
+
+        // unlink every odd node
+        let mut curr = head;
+        while let Some(idx) = curr {
+            curr = arena[idx].next;
+            if arena[idx].val % 2 == 1 {
+                let Node { prev, next, .. } = arena.remove(idx).unwrap();
+                match prev {
+                    Some(prev) => arena[prev].next = next,
+                    None => head = next,
+                }
+                if let Some(next) = next {
+                    arena[next].prev = prev;
+                }
+            }
+        }
+        assert_eq!(arena.len(), 5);
+
+        // swap the values of the first two nodes through their links
+        let second = arena[head.unwrap()].next.unwrap();
+        let (first, second) = arena.get2_mut(head.unwrap(), second);
+        std::mem::swap(&mut first.unwrap().val, &mut second.unwrap().val);
+
+        let mut vals = std::vec::Vec::new();
+        let mut curr = head;
+        while let Some(idx) = curr {
+            vals.push(arena[idx].val);
+            curr = arena[idx].next;
+        }
+        assert_eq!(vals, [2, 0, 4, 6, 8]);
+    }
+}
@@ -0,0 +1,459 @@
+use super::{RawVec, Vec};
+use std::iter::FusedIterator;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const WORD_BITS: usize = 64;
+// bits covered by one entry of the rank index
+const BLOCK_BITS: usize = 512;
+const BLOCK_WORDS: usize = BLOCK_BITS / WORD_BITS;
+
+// Bits are packed into u64 words, bits past len in the last word are always 0.
+//
+// blocks[b] is the number of ones before block b. push/pop and set are O(1),
+// set only marks the blocks after it as stale and the next rank or select
+// fixes them up, bulk ops rebuild it. The index is kept in atomics so the
+// fix-up through &self leaves BitVec Sync.
+pub struct BitVec {
+    words: RawVec<u64>,
+    len: usize,
+    ones: usize,
+    blocks: Vec<AtomicUsize>,
+    // first stale entry of blocks, usize::MAX if none is
+    stale: AtomicUsize,
+}
+
+fn nwords(len: usize) -> usize {
+    len.div_ceil(WORD_BITS)
+}
+
+impl BitVec {
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    pub fn with_capacity(bits: usize) -> Self {
+        Self {
+            words: RawVec::with_capacity(nwords(bits)),
+            len: 0,
+            ones: 0,
+            blocks: Vec::new(),
+            stale: AtomicUsize::new(usize::MAX),
+        }
+    }
+
+    pub fn from_elem(len: usize, bit: bool) -> Self {
+        let mut bv = Self::with_capacity(len);
+        unsafe {
+            std::ptr::write_bytes(bv.words.as_ptr(), if bit { 0xff } else { 0 }, nwords(len));
+        }
+        bv.len = len;
+        bv.clear_unused();
+        bv.rebuild();
+        bv
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.words.cap() * WORD_BITS
+    }
+
+    pub fn as_words(&self) -> &[u64] {
+        unsafe { std::slice::from_raw_parts(self.words.as_ptr(), nwords(self.len)) }
+    }
+
+    fn as_words_mut(&mut self) -> &mut [u64] {
+        unsafe { std::slice::from_raw_parts_mut(self.words.as_ptr(), nwords(self.len)) }
+    }
+
+    // zero the bits past len in the last word
+    fn clear_unused(&mut self) {
+        let rem = self.len % WORD_BITS;
+        if rem != 0 {
+            let last = nwords(self.len) - 1;
+            self.as_words_mut()[last] &= (1 << rem) - 1;
+        }
+    }
+
+    fn rebuild(&mut self) {
+        let mut blocks = Vec::new();
+        let mut ones = 0;
+        for (i, word) in self.as_words().iter().enumerate() {
+            if i % BLOCK_WORDS == 0 {
+                blocks.push(AtomicUsize::new(ones));
+            }
+            ones += word.count_ones() as usize;
+        }
+        self.blocks = blocks;
+        *self.stale.get_mut() = usize::MAX;
+        self.ones = ones;
+    }
+
+    // the rank index, with the entries after a set brought up to date
+    //
+    // Readers racing on the fix-up all store the same counts, the words can't
+    // change under &self. stale is only cleared once the entries are written.
+    fn blocks(&self) -> &[AtomicUsize] {
+        let stale = self.stale.load(Ordering::Acquire);
+        if stale != usize::MAX {
+            let words = self.as_words();
+            for b in stale..self.blocks.len() {
+                let ones = words[(b - 1) * BLOCK_WORDS..b * BLOCK_WORDS]
+                    .iter()
+                    .map(|w| w.count_ones() as usize)
+                    .sum::<usize>();
+                let before = self.blocks[b - 1].load(Ordering::Relaxed);
+                self.blocks[b].store(before + ones, Ordering::Relaxed);
+            }
+            let _ = self.stale.compare_exchange(
+                stale,
+                usize::MAX,
+                Ordering::Release,
+                Ordering::Relaxed,
+            );
+        }
+        &self.blocks
+    }
+
+    pub fn push(&mut self, bit: bool) {
+        if self.len % WORD_BITS == 0 {
+            let target_cap = nwords(self.len) + 1;
+            if target_cap > self.words.cap() {
+                self.words
+                    .reserve(std::cmp::max(2 * self.words.cap(), target_cap));
+            }
+            unsafe {
+                *self.words.as_ptr().add(nwords(self.len)) = 0;
+            }
+        }
+        if self.len % BLOCK_BITS == 0 {
+            self.blocks.push(AtomicUsize::new(self.ones));
+        }
+
+        let idx = self.len;
+        self.len += 1;
+        if bit {
+            self.as_words_mut()[idx / WORD_BITS] |= 1 << (idx % WORD_BITS);
+            self.ones += 1;
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<bool> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let idx = self.len - 1;
+        let bit = self.get(idx).unwrap();
+        if bit {
+            self.as_words_mut()[idx / WORD_BITS] &= !(1 << (idx % WORD_BITS));
+            self.ones -= 1;
+        }
+        self.len -= 1;
+        if self.len % BLOCK_BITS == 0 {
+            self.blocks.pop();
+        }
+        Some(bit)
+    }
+
+    pub fn get(&self, idx: usize) -> Option<bool> {
+        if idx < self.len {
+            Some(self.as_words()[idx / WORD_BITS] >> (idx % WORD_BITS) & 1 == 1)
+        } else {
+            None
+        }
+    }
+
+    pub fn set(&mut self, idx: usize, bit: bool) {
+        assert!(idx < self.len, "index out of bounds");
+        if self.get(idx) == Some(bit) {
+            return;
+        }
+
+        let mask = 1 << (idx % WORD_BITS);
+        if bit {
+            self.as_words_mut()[idx / WORD_BITS] |= mask;
+            self.ones += 1;
+        } else {
+            self.as_words_mut()[idx / WORD_BITS] &= !mask;
+            self.ones -= 1;
+        }
+        let stale = self.stale.get_mut();
+        *stale = (*stale).min(idx / BLOCK_BITS + 1);
+    }
+
+    fn zip_with(&mut self, other: &BitVec, f: impl Fn(u64, u64) -> u64) {
+        assert_eq!(self.len, other.len, "length mismatch");
+        for (a, b) in self.as_words_mut().iter_mut().zip(other.as_words()) {
+            *a = f(*a, *b);
+        }
+        self.rebuild();
+    }
+
+    pub fn and(&mut self, other: &BitVec) {
+        self.zip_with(other, |a, b| a & b);
+    }
+
+    pub fn or(&mut self, other: &BitVec) {
+        self.zip_with(other, |a, b| a | b);
+    }
+
+    pub fn xor(&mut self, other: &BitVec) {
+        self.zip_with(other, |a, b| a ^ b);
+    }
+
+    pub fn not(&mut self) {
+        self.as_words_mut().iter_mut().for_each(|w| *w = !*w);
+        self.clear_unused();
+        self.rebuild();
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.ones
+    }
+
+    pub fn count_zeros(&self) -> usize {
+        self.len - self.ones
+    }
+
+    // number of ones in [0, idx)
+    pub fn rank1(&self, idx: usize) -> usize {
+        assert!(idx <= self.len, "index out of bounds");
+        let block = idx / BLOCK_BITS;
+        let blocks = self.blocks();
+        if block == blocks.len() {
+            return self.ones;
+        }
+
+        let words = self.as_words();
+        let (word, rem) = (idx / WORD_BITS, idx % WORD_BITS);
+        let mut rank = blocks[block].load(Ordering::Relaxed);
+        rank += words[block * BLOCK_WORDS..word]
+            .iter()
+            .map(|w| w.count_ones() as usize)
+            .sum::<usize>();
+        if rem != 0 {
+            rank += (words[word] & ((1 << rem) - 1)).count_ones() as usize;
+        }
+        rank
+    }
+
+    pub fn rank0(&self, idx: usize) -> usize {
+        idx - self.rank1(idx)
+    }
+
+    // position of the k-th one (0-based)
+    pub fn select1(&self, k: usize) -> Option<usize> {
+        if k >= self.ones {
+            return None;
+        }
+
+        // last block with fewer than k + 1 ones before it
+        let blocks = self.blocks();
+        let block = blocks.partition_point(|before| before.load(Ordering::Relaxed) <= k) - 1;
+        let mut k = k - blocks[block].load(Ordering::Relaxed);
+        for (i, &word) in self.as_words()[block * BLOCK_WORDS..].iter().enumerate() {
+            let ones = word.count_ones() as usize;
+            if k < ones {
+                let mut word = word;
+                for _ in 0..k {
+                    word &= word - 1;
+                }
+                let pos = (block * BLOCK_WORDS + i) * WORD_BITS + word.trailing_zeros() as usize;
+                return Some(pos);
+            }
+            k -= ones;
+        }
+        unreachable!()
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            bv: self,
+            start: 0,
+            end: self.len,
+        }
+    }
+}
+
+impl Default for BitVec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Iter<'a> {
+    bv: &'a BitVec,
+    start: usize,
+    end: usize,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = bool;
+
+    fn next(&mut self) -> Option<bool> {
+        if self.start == self.end {
+            None
+        } else {
+            self.start += 1;
+            self.bv.get(self.start - 1)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.end - self.start, Some(self.end - self.start))
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<bool> {
+        if self.start == self.end {
+            None
+        } else {
+            self.end -= 1;
+            self.bv.get(self.end)
+        }
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+impl FusedIterator for Iter<'_> {}
+
+#[cfg(test)]
+mod test {
+    use super::BitVec;
+    use rand::random;
+
+    fn gen(len: usize) -> (BitVec, std::vec::Vec<bool>) {
+        let mut bv = BitVec::new();
+        let mut bools = std::vec::Vec::new();
+        for _ in 0..len {
+            let bit = random::<u32>() % 4 == 0;
+            bv.push(bit);
+            bools.push(bit);
+        }
+        (bv, bools)
+    }
+
+    fn check(bv: &BitVec, bools: &[bool]) {
+        assert_eq!(bv.len(), bools.len());
+        assert!(bv.iter().eq(bools.iter().copied()));
+        assert_eq!(bv.count_ones(), bools.iter().filter(|b| **b).count());
+
+        let mut rank = 0;
+        let mut ones = std::vec::Vec::new();
+        for (i, &bit) in bools.iter().enumerate() {
+            assert_eq!(bv.rank1(i), rank);
+            if bit {
+                ones.push(i);
+                rank += 1;
+            }
+        }
+        assert_eq!(bv.rank1(bools.len()), rank);
+        for (k, &pos) in ones.iter().enumerate() {
+            assert_eq!(bv.select1(k), Some(pos));
+        }
+        assert_eq!(bv.select1(ones.len()), None);
+    }
+
+    #[test]
+    fn test() {
+        let (mut bv, mut bools) = gen(0);
+        for _ in 0..3000 {
+            match random::<u32>() % 4 {
+                0 | 1 => {
+                    let bit = random();
+                    bv.push(bit);
+                    bools.push(bit);
+                }
+                2 => assert_eq!(bv.pop(), bools.pop()),
+                _ => {
+                    if !bools.is_empty() {
+                        let idx = random::<usize>() % bools.len();
+                        let bit = random();
+                        bv.set(idx, bit);
+                        bools[idx] = bit;
+                    }
+                }
+            }
+        }
+        check(&bv, &bools);
+        assert_eq!(bv.get(bools.len()), None);
+    }
+
+    #[test]
+    fn set_then_rank() {
+        // the rank index is fixed up lazily, query between the changes
+        let (mut bv, mut bools) = gen(2000);
+        for _ in 0..200 {
+            let idx = random::<usize>() % bools.len();
+            let bit = random();
+            bv.set(idx, bit);
+            bools[idx] = bit;
+            if random() {
+                bv.push(bit);
+                bools.push(bit);
+            }
+            let idx = random::<usize>() % (bools.len() + 1);
+            assert_eq!(bv.rank1(idx), bools[..idx].iter().filter(|b| **b).count());
+        }
+        check(&bv, &bools);
+    }
+
+    #[test]
+    fn send_sync() {
+        fn assert<T: Send + Sync>() {}
+        assert::<BitVec>();
+    }
+
+    #[test]
+    fn shared_rank() {
+        // readers fix up the index concurrently after a set
+        let (mut bv, mut bools) = gen(5000);
+        for i in (0..bools.len()).step_by(7) {
+            bv.set(i, true);
+            bools[i] = true;
+        }
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| check(&bv, &bools));
+            }
+        });
+    }
+
+    #[test]
+    fn bulk() {
+        for len in [0, 1, 63, 64, 65, 511, 512, 513, 2000] {
+            let (mut a, mut a_bools) = gen(len);
+            let (b, b_bools) = gen(len);
+            let zip = |x: &mut std::vec::Vec<bool>, f: fn(bool, bool) -> bool| {
+                x.iter_mut().zip(&b_bools).for_each(|(x, y)| *x = f(*x, *y))
+            };
+
+            a.or(&b);
+            zip(&mut a_bools, |x, y| x | y);
+            check(&a, &a_bools);
+            a.xor(&b);
+            zip(&mut a_bools, |x, y| x ^ y);
+            check(&a, &a_bools);
+            a.and(&b);
+            zip(&mut a_bools, |x, y| x & y);
+            check(&a, &a_bools);
+            a.not();
+            a_bools.iter_mut().for_each(|x| *x = !*x);
+            check(&a, &a_bools);
+
+            let ones = BitVec::from_elem(len, true);
+            assert_eq!(ones.count_ones(), len);
+            assert_eq!(ones.rank0(len), 0);
+            check(&BitVec::from_elem(len, false), &vec![false; len]);
+        }
+    }
+}
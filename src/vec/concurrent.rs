@@ -0,0 +1,239 @@
+use super::RawVec;
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+
+// bucket b holds FIRST << b slots, so the buckets cover all of usize
+const FIRST_BITS: u32 = 5;
+const FIRST: usize = 1 << FIRST_BITS;
+const BUCKETS: usize = (usize::BITS - FIRST_BITS) as usize;
+
+struct Slot<T> {
+    ready: AtomicBool,
+    val: UnsafeCell<MaybeUninit<T>>,
+}
+
+// Append-only vector, push and get only take &self and never lock.
+//
+// Buckets are allocated on demand and never move or shrink, so a reference
+// from get stays valid while other threads push. An index is claimed with
+// fetch_add, the value is visible to get once its ready flag is set.
+pub struct ConcurrentVec<T> {
+    buckets: [AtomicPtr<Slot<T>>; BUCKETS],
+    // indices handed out by push, some may still be written
+    reserved: AtomicUsize,
+}
+
+unsafe impl<T: Send> Send for ConcurrentVec<T> {}
+unsafe impl<T: Send + Sync> Sync for ConcurrentVec<T> {}
+
+fn bucket_len(bucket: usize) -> usize {
+    FIRST << bucket
+}
+
+// (bucket, offset) of an index
+fn locate(idx: usize) -> (usize, usize) {
+    let idx = idx + FIRST;
+    let bucket = (usize::BITS - 1 - idx.leading_zeros() - FIRST_BITS) as usize;
+    (bucket, idx - bucket_len(bucket))
+}
+
+impl<T> ConcurrentVec<T> {
+    pub fn new() -> Self {
+        Self {
+            buckets: [(); BUCKETS].map(|_| AtomicPtr::new(ptr::null_mut())),
+            reserved: AtomicUsize::new(0),
+        }
+    }
+
+    // number of pushes so far, including ones that are not visible yet
+    pub fn len(&self) -> usize {
+        self.reserved.load(Ordering::Acquire)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // allocate the bucket if no other thread did it yet
+    fn bucket(&self, bucket: usize) -> *mut Slot<T> {
+        let ptr = self.buckets[bucket].load(Ordering::Acquire);
+        if !ptr.is_null() {
+            return ptr;
+        }
+
+        let len = bucket_len(bucket);
+        let new = RawVec::<Slot<T>>::with_capacity(len).into_raw();
+        for i in 0..len {
+            unsafe {
+                ptr::write(
+                    new.add(i),
+                    Slot {
+                        ready: AtomicBool::new(false),
+                        val: UnsafeCell::new(MaybeUninit::uninit()),
+                    },
+                );
+            }
+        }
+        match self.buckets[bucket].compare_exchange(
+            ptr::null_mut(),
+            new,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => new,
+            Err(winner) => {
+                drop(unsafe { RawVec::from_raw_parts(new, len) });
+                winner
+            }
+        }
+    }
+
+    // append a value and return its index
+    pub fn push(&self, val: T) -> usize {
+        let idx = self.reserved.fetch_add(1, Ordering::AcqRel);
+        assert!(idx <= usize::MAX - FIRST, "capacity overflow");
+        let (bucket, offset) = locate(idx);
+        unsafe {
+            let slot = &*self.bucket(bucket).add(offset);
+            (*slot.val.get()).write(val);
+            slot.ready.store(true, Ordering::Release);
+        }
+        idx
+    }
+
+    fn slot(&self, idx: usize) -> Option<&Slot<T>> {
+        if idx >= self.len() {
+            return None;
+        }
+        let (bucket, offset) = locate(idx);
+        let ptr = self.buckets[bucket].load(Ordering::Acquire);
+        if ptr.is_null() {
+            None
+        } else {
+            unsafe { Some(&*ptr.add(offset)) }
+        }
+    }
+
+    // None if idx was not pushed, or the push has not finished
+    pub fn get(&self, idx: usize) -> Option<&T> {
+        let slot = self.slot(idx)?;
+        if slot.ready.load(Ordering::Acquire) {
+            unsafe { Some((*slot.val.get()).assume_init_ref()) }
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, idx: usize) -> Option<&mut T> {
+        let slot = self.slot(idx)?;
+        if slot.ready.load(Ordering::Acquire) {
+            unsafe { Some((*slot.val.get()).assume_init_mut()) }
+        } else {
+            None
+        }
+    }
+
+    // the visible values in index order
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        (0..self.len()).filter_map(move |idx| self.get(idx).map(|val| (idx, val)))
+    }
+}
+
+impl<T> Default for ConcurrentVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for ConcurrentVec<T> {
+    fn drop(&mut self) {
+        for (bucket, ptr) in self.buckets.iter_mut().enumerate() {
+            let ptr = *ptr.get_mut();
+            if ptr.is_null() {
+                continue;
+            }
+            let len = bucket_len(bucket);
+            unsafe {
+                for i in 0..len {
+                    let slot = &mut *ptr.add(i);
+                    if *slot.ready.get_mut() {
+                        slot.val.get_mut().assume_init_drop();
+                    }
+                }
+                drop(RawVec::from_raw_parts(ptr, len));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{locate, ConcurrentVec, FIRST};
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_locate() {
+        assert_eq!(locate(0), (0, 0));
+        assert_eq!(locate(FIRST - 1), (0, FIRST - 1));
+        assert_eq!(locate(FIRST), (1, 0));
+        assert_eq!(locate(3 * FIRST - 1), (1, 2 * FIRST - 1));
+        assert_eq!(locate(3 * FIRST), (2, 0));
+        assert_eq!(locate(usize::MAX - FIRST).0, super::BUCKETS - 1);
+    }
+
+    #[test]
+    fn test() {
+        let vec = ConcurrentVec::new();
+        for i in 0..1000 {
+            assert_eq!(vec.push(i * 2), i);
+        }
+        assert_eq!(vec.len(), 1000);
+        assert_eq!(vec.get(999), Some(&1998));
+        assert_eq!(vec.get(1000), None);
+        assert!(vec.iter().all(|(i, val)| *val == i * 2));
+    }
+
+    #[test]
+    fn concurrent() {
+        const THREADS: usize = 8;
+        const PUSHES: usize = 10000;
+
+        let vec = ConcurrentVec::new();
+        let rc = Arc::new(());
+        thread::scope(|s| {
+            for t in 0..THREADS {
+                let (vec, rc) = (&vec, &rc);
+                s.spawn(move || {
+                    for i in 0..PUSHES {
+                        let idx = vec.push((t, i, rc.clone()));
+                        // visible to the pushing thread right away
+                        assert_eq!(vec.get(idx).unwrap().1, i);
+                    }
+                });
+                // a reader running alongside the writers
+                s.spawn(move || {
+                    for idx in 0..PUSHES {
+                        if let Some((t, i, _)) = vec.get(idx) {
+                            assert!(*t < THREADS && *i < PUSHES);
+                        }
+                    }
+                });
+            }
+        });
+
+        assert_eq!(vec.len(), THREADS * PUSHES);
+        let mut seen = vec![0; THREADS];
+        for (_, (t, i, _)) in vec.iter() {
+            // each thread's values are in push order
+            assert_eq!(seen[*t], *i);
+            seen[*t] += 1;
+        }
+        assert_eq!(seen, vec![PUSHES; THREADS]);
+        assert_eq!(Arc::strong_count(&rc), THREADS * PUSHES + 1);
+        drop(vec);
+        assert_eq!(Arc::strong_count(&rc), 1);
+    }
+}
@@ -0,0 +1,530 @@
+use super::RawVec;
+use std::iter::FusedIterator;
+use std::ops::{Index, IndexMut};
+use std::ptr;
+use std::slice;
+
+// growable ring buffer, elements live in [head, head + len) modulo capacity
+pub struct VecDeque<T> {
+    buf: RawVec<T>,
+    head: usize,
+    len: usize,
+}
+
+impl<T> VecDeque<T> {
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    pub fn with_capacity(cap: usize) -> Self {
+        Self {
+            buf: RawVec::with_capacity(cap),
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.cap()
+    }
+
+    fn ptr(&self) -> *mut T {
+        self.buf.as_ptr()
+    }
+
+    // physical index of the idx-th element, written this way so it does not
+    // overflow when cap is usize::MAX for ZST
+    fn wrap_idx(&self, idx: usize) -> usize {
+        let cap = self.capacity();
+        if idx < cap - self.head {
+            self.head + idx
+        } else {
+            idx - (cap - self.head)
+        }
+    }
+
+    fn is_contiguous(&self) -> bool {
+        self.len <= self.capacity() - self.head
+    }
+
+    pub fn reserve(&mut self, target_cap: usize) {
+        let old_cap = self.capacity();
+        if target_cap <= old_cap {
+            return;
+        }
+        let contiguous = self.is_contiguous();
+        self.buf.reserve(target_cap);
+
+        if !contiguous {
+            // [back | .. | front] => move the shorter part into the new space
+            let new_cap = self.capacity();
+            let head_len = old_cap - self.head;
+            let tail_len = self.len - head_len;
+            unsafe {
+                if tail_len <= new_cap - old_cap && tail_len < head_len {
+                    ptr::copy_nonoverlapping(self.ptr(), self.ptr().add(old_cap), tail_len);
+                } else {
+                    let new_head = new_cap - head_len;
+                    ptr::copy(
+                        self.ptr().add(self.head),
+                        self.ptr().add(new_head),
+                        head_len,
+                    );
+                    self.head = new_head;
+                }
+            }
+        }
+    }
+
+    fn grow_one(&mut self) {
+        if self.len == self.capacity() {
+            let target_cap = self.len.checked_add(1).expect("capacity overflow");
+            self.reserve(std::cmp::max(2 * self.len, target_cap));
+        }
+    }
+
+    pub fn push_back(&mut self, val: T) {
+        self.grow_one();
+        unsafe {
+            ptr::write(self.ptr().add(self.wrap_idx(self.len)), val);
+        }
+        self.len += 1;
+    }
+
+    pub fn push_front(&mut self, val: T) {
+        self.grow_one();
+        self.head = self.wrap_idx(self.capacity() - 1);
+        unsafe {
+            ptr::write(self.ptr().add(self.head), val);
+        }
+        self.len += 1;
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            self.len -= 1;
+            unsafe { Some(ptr::read(self.ptr().add(self.wrap_idx(self.len)))) }
+        }
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            let head = self.head;
+            self.head = self.wrap_idx(1);
+            self.len -= 1;
+            unsafe { Some(ptr::read(self.ptr().add(head))) }
+        }
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&T> {
+        if idx < self.len {
+            unsafe { Some(&*self.ptr().add(self.wrap_idx(idx))) }
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, idx: usize) -> Option<&mut T> {
+        if idx < self.len {
+            unsafe { Some(&mut *self.ptr().add(self.wrap_idx(idx))) }
+        } else {
+            None
+        }
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        self.get_mut(0)
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.get(self.len.wrapping_sub(1))
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        self.get_mut(self.len.wrapping_sub(1))
+    }
+
+    // the elements in order, the second slice is empty if they are contiguous
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let head_len = std::cmp::min(self.len, self.capacity() - self.head);
+        unsafe {
+            (
+                slice::from_raw_parts(self.ptr().add(self.head), head_len),
+                slice::from_raw_parts(self.ptr(), self.len - head_len),
+            )
+        }
+    }
+
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        let head_len = std::cmp::min(self.len, self.capacity() - self.head);
+        unsafe {
+            (
+                slice::from_raw_parts_mut(self.ptr().add(self.head), head_len),
+                slice::from_raw_parts_mut(self.ptr(), self.len - head_len),
+            )
+        }
+    }
+
+    pub fn make_contiguous(&mut self) -> &mut [T] {
+        if !self.is_contiguous() {
+            // [back | .. | front] => [back | front] => [front | back]
+            let head_len = self.capacity() - self.head;
+            let tail_len = self.len - head_len;
+            unsafe {
+                ptr::copy(
+                    self.ptr().add(self.head),
+                    self.ptr().add(tail_len),
+                    head_len,
+                );
+                slice::from_raw_parts_mut(self.ptr(), self.len).rotate_left(tail_len);
+            }
+            self.head = 0;
+        }
+        unsafe { slice::from_raw_parts_mut(self.ptr().add(self.head), self.len) }
+    }
+
+    // move one element from the front to the back
+    fn rotate_one_left(&mut self) {
+        unsafe {
+            let src = self.ptr().add(self.head);
+            let dst = self.ptr().add(self.wrap_idx(self.len));
+            self.head = self.wrap_idx(1);
+            ptr::copy(src, dst, 1);
+        }
+    }
+
+    fn rotate_one_right(&mut self) {
+        unsafe {
+            let src = self.ptr().add(self.wrap_idx(self.len - 1));
+            self.head = self.wrap_idx(self.capacity() - 1);
+            ptr::copy(src, self.ptr().add(self.head), 1);
+        }
+    }
+
+    // rotate so that the n-th element becomes the first one
+    pub fn rotate_left(&mut self, n: usize) {
+        assert!(n <= self.len, "rotate out of bounds");
+        if n <= self.len - n {
+            (0..n).for_each(|_| self.rotate_one_left());
+        } else {
+            (0..self.len - n).for_each(|_| self.rotate_one_right());
+        }
+    }
+
+    pub fn rotate_right(&mut self, n: usize) {
+        assert!(n <= self.len, "rotate out of bounds");
+        self.rotate_left(self.len - n);
+    }
+
+    pub fn clear(&mut self) {
+        let (front, back) = self.as_mut_slices();
+        let (front, back) = (front as *mut [T], back as *mut [T]);
+        self.head = 0;
+        self.len = 0;
+        unsafe {
+            ptr::drop_in_place(front);
+            ptr::drop_in_place(back);
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        let (front, back) = self.as_slices();
+        Iter {
+            front: front.iter(),
+            back: back.iter(),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        let (front, back) = self.as_mut_slices();
+        IterMut {
+            front: front.iter_mut(),
+            back: back.iter_mut(),
+        }
+    }
+}
+
+impl<T> Default for VecDeque<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for VecDeque<T> {
+    fn drop(&mut self) {
+        // the buffer is freed by RawVec
+        self.clear();
+    }
+}
+
+impl<T> Index<usize> for VecDeque<T> {
+    type Output = T;
+    fn index(&self, idx: usize) -> &T {
+        self.get(idx).expect("index out of bounds")
+    }
+}
+
+impl<T> IndexMut<usize> for VecDeque<T> {
+    fn index_mut(&mut self, idx: usize) -> &mut T {
+        self.get_mut(idx).expect("index out of bounds")
+    }
+}
+
+pub struct Iter<'a, T> {
+    front: slice::Iter<'a, T>,
+    back: slice::Iter<'a, T>,
+}
+
+pub struct IterMut<'a, T> {
+    front: slice::IterMut<'a, T>,
+    back: slice::IterMut<'a, T>,
+}
+
+pub struct IntoIter<T>(VecDeque<T>);
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.front.next().or_else(|| self.back.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.front.len() + self.back.len();
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.back.next_back().or_else(|| self.front.next_back())
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        self.front.next().or_else(|| self.back.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.front.len() + self.back.len();
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IterMut<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.back.next_back().or_else(|| self.front.next_back())
+    }
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+impl<T> FusedIterator for IterMut<'_, T> {}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.0.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.0.pop_back()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> FusedIterator for IntoIter<T> {}
+
+impl<T> IntoIterator for VecDeque<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a VecDeque<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut VecDeque<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::VecDeque;
+    use rand::random;
+    use std::rc::Rc;
+
+    fn check(my_deque: &mut VecDeque<i32>, std_deque: &std::collections::VecDeque<i32>) {
+        assert_eq!(my_deque.len(), std_deque.len());
+        let (a, b) = my_deque.as_slices();
+        assert_eq!(
+            [a, b].concat(),
+            std_deque.iter().copied().collect::<Vec<_>>()
+        );
+        assert!(my_deque.iter().eq(std_deque.iter()));
+        assert!(my_deque.iter().rev().eq(std_deque.iter().rev()));
+    }
+
+    fn rand_op(my_deque: &mut VecDeque<i32>, std_deque: &mut std::collections::VecDeque<i32>) {
+        let len = my_deque.len();
+        match random::<u32>() % 10 {
+            0 | 1 => {
+                let val = random();
+                my_deque.push_back(val);
+                std_deque.push_back(val);
+            }
+            2 | 3 => {
+                let val = random();
+                my_deque.push_front(val);
+                std_deque.push_front(val);
+            }
+            4 => assert_eq!(my_deque.pop_back(), std_deque.pop_back()),
+            5 => assert_eq!(my_deque.pop_front(), std_deque.pop_front()),
+            6 => {
+                if len > 0 {
+                    let idx = random::<usize>() % len;
+                    assert_eq!(my_deque[idx], std_deque[idx]);
+                    let val = random();
+                    my_deque[idx] = val;
+                    std_deque[idx] = val;
+                }
+                assert_eq!(my_deque.get(len), None);
+                assert_eq!(my_deque.front(), std_deque.front());
+                assert_eq!(my_deque.back(), std_deque.back());
+            }
+            7 => {
+                let n = random::<usize>() % (len + 1);
+                my_deque.rotate_left(n);
+                std_deque.rotate_left(n);
+            }
+            8 => {
+                let n = random::<usize>() % (len + 1);
+                my_deque.rotate_right(n);
+                std_deque.rotate_right(n);
+            }
+            _ => {
+                assert_eq!(my_deque.make_contiguous(), std_deque.make_contiguous());
+                assert!(my_deque.as_slices().1.is_empty());
+            }
+        }
+        check(my_deque, std_deque);
+    }
+
+    #[test]
+    fn test() {
+        for cap in [0, 1, 7, 16] {
+            let mut my_deque = VecDeque::with_capacity(cap);
+            let mut std_deque = std::collections::VecDeque::new();
+            for _ in 0..10000 {
+                rand_op(&mut my_deque, &mut std_deque);
+            }
+        }
+    }
+
+    #[test]
+    fn grow_wrapped() {
+        for front in 0..8 {
+            let mut my_deque = VecDeque::with_capacity(8);
+            let mut std_deque = std::collections::VecDeque::new();
+            for i in 0..8 {
+                if i < front {
+                    my_deque.push_front(i);
+                    std_deque.push_front(i);
+                } else {
+                    my_deque.push_back(i);
+                    std_deque.push_back(i);
+                }
+            }
+            assert_eq!(my_deque.capacity(), 8);
+            my_deque.push_back(100);
+            std_deque.push_back(100);
+            check(&mut my_deque, &std_deque);
+        }
+    }
+
+    #[test]
+    fn iter() {
+        let mut my_deque = VecDeque::new();
+        for i in 0..10 {
+            my_deque.push_front(i);
+        }
+        for elem in &mut my_deque {
+            *elem *= 2;
+        }
+        let collected: Vec<_> = (&my_deque).into_iter().copied().collect();
+        assert_eq!(collected, [18, 16, 14, 12, 10, 8, 6, 4, 2, 0]);
+        let mut into_iter = my_deque.into_iter();
+        assert_eq!(into_iter.next(), Some(18));
+        assert_eq!(into_iter.next_back(), Some(0));
+        assert_eq!(into_iter.len(), 8);
+    }
+
+    #[test]
+    fn zst_and_drop() {
+        let mut my_deque = VecDeque::new();
+        for _ in 0..100 {
+            my_deque.push_front(());
+            my_deque.push_back(());
+        }
+        my_deque.rotate_left(50);
+        assert_eq!(my_deque.make_contiguous().len(), 200);
+        assert_eq!(my_deque.pop_front(), Some(()));
+        assert_eq!(my_deque.iter().count(), 199);
+
+        let rc = Rc::new(());
+        let mut my_deque = VecDeque::with_capacity(4);
+        for _ in 0..3 {
+            my_deque.push_front(rc.clone());
+            my_deque.push_back(rc.clone());
+        }
+        my_deque.pop_front();
+        assert_eq!(Rc::strong_count(&rc), 6);
+        drop(my_deque);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+}
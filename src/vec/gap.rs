@@ -0,0 +1,372 @@
+use super::RawVec;
+use std::iter::Chain;
+use std::ops::{Index, IndexMut};
+use std::ptr;
+use std::slice;
+
+// Elements are kept in [0, gap_start) and [gap_end, cap), the gap sits at the
+// cursor and only moves when the cursor does, so inserts and deletes next to
+// the cursor don't shift anything.
+pub struct GapBuffer<T> {
+    buf: RawVec<T>,
+    gap_start: usize,
+    gap_end: usize,
+}
+
+impl<T> GapBuffer<T> {
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    pub fn with_capacity(cap: usize) -> Self {
+        let buf = RawVec::with_capacity(cap);
+        Self {
+            gap_start: 0,
+            gap_end: buf.cap(),
+            buf,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.cap() - (self.gap_end - self.gap_start)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.cap()
+    }
+
+    // elements before the cursor
+    pub fn cursor(&self) -> usize {
+        self.gap_start
+    }
+
+    fn ptr(&self) -> *mut T {
+        self.buf.as_ptr()
+    }
+
+    fn after_len(&self) -> usize {
+        self.buf.cap() - self.gap_end
+    }
+
+    // physical index of the idx-th element
+    fn phys_idx(&self, idx: usize) -> usize {
+        if idx < self.gap_start {
+            idx
+        } else {
+            idx + (self.gap_end - self.gap_start)
+        }
+    }
+
+    pub fn reserve(&mut self, target_cap: usize) {
+        let old_cap = self.buf.cap();
+        if target_cap <= old_cap {
+            return;
+        }
+
+        let after_len = self.after_len();
+        self.buf.reserve(target_cap);
+        let new_gap_end = self.buf.cap() - after_len;
+        unsafe {
+            ptr::copy(
+                self.ptr().add(self.gap_end),
+                self.ptr().add(new_gap_end),
+                after_len,
+            );
+        }
+        self.gap_end = new_gap_end;
+    }
+
+    fn grow_one(&mut self) {
+        if self.gap_start == self.gap_end {
+            let len = self.len();
+            let target_cap = len.checked_add(1).expect("capacity overflow");
+            self.reserve(std::cmp::max(2 * len, target_cap));
+        }
+    }
+
+    pub fn set_cursor(&mut self, pos: usize) {
+        assert!(pos <= self.len(), "cursor out of bounds");
+        let gap = self.gap_end - self.gap_start;
+        unsafe {
+            if pos < self.gap_start {
+                // move [pos, gap_start) behind the gap
+                let n = self.gap_start - pos;
+                ptr::copy(self.ptr().add(pos), self.ptr().add(pos + gap), n);
+            } else {
+                // move [gap_end, pos + gap) in front of the gap
+                let n = pos - self.gap_start;
+                ptr::copy(
+                    self.ptr().add(self.gap_end),
+                    self.ptr().add(self.gap_start),
+                    n,
+                );
+            }
+        }
+        self.gap_start = pos;
+        self.gap_end = pos + gap;
+    }
+
+    pub fn move_left(&mut self, n: usize) {
+        let pos = self.gap_start.checked_sub(n).expect("cursor out of bounds");
+        self.set_cursor(pos);
+    }
+
+    pub fn move_right(&mut self, n: usize) {
+        self.set_cursor(self.gap_start + n);
+    }
+
+    // insert before the cursor, the cursor moves past the new element
+    pub fn insert(&mut self, val: T) {
+        self.grow_one();
+        unsafe {
+            ptr::write(self.ptr().add(self.gap_start), val);
+        }
+        self.gap_start += 1;
+    }
+
+    pub fn insert_at(&mut self, pos: usize, val: T) {
+        self.set_cursor(pos);
+        self.insert(val);
+    }
+
+    // remove the element before the cursor
+    pub fn delete_before(&mut self) -> Option<T> {
+        if self.gap_start == 0 {
+            None
+        } else {
+            self.gap_start -= 1;
+            unsafe { Some(ptr::read(self.ptr().add(self.gap_start))) }
+        }
+    }
+
+    // remove the element after the cursor
+    pub fn delete_after(&mut self) -> Option<T> {
+        if self.gap_end == self.buf.cap() {
+            None
+        } else {
+            self.gap_end += 1;
+            unsafe { Some(ptr::read(self.ptr().add(self.gap_end - 1))) }
+        }
+    }
+
+    pub fn remove(&mut self, pos: usize) -> T {
+        assert!(pos < self.len(), "remove index out of bounds");
+        self.set_cursor(pos);
+        self.delete_after().unwrap()
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&T> {
+        if idx < self.len() {
+            unsafe { Some(&*self.ptr().add(self.phys_idx(idx))) }
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, idx: usize) -> Option<&mut T> {
+        if idx < self.len() {
+            unsafe { Some(&mut *self.ptr().add(self.phys_idx(idx))) }
+        } else {
+            None
+        }
+    }
+
+    // elements before and after the cursor
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        unsafe {
+            (
+                slice::from_raw_parts(self.ptr(), self.gap_start),
+                slice::from_raw_parts(self.ptr().add(self.gap_end), self.after_len()),
+            )
+        }
+    }
+
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        unsafe {
+            (
+                slice::from_raw_parts_mut(self.ptr(), self.gap_start),
+                slice::from_raw_parts_mut(self.ptr().add(self.gap_end), self.after_len()),
+            )
+        }
+    }
+
+    // move the gap to the end
+    pub fn make_contiguous(&mut self) -> &mut [T] {
+        self.set_cursor(self.len());
+        self.as_mut_slices().0
+    }
+
+    pub fn iter(&self) -> Chain<slice::Iter<'_, T>, slice::Iter<'_, T>> {
+        let (before, after) = self.as_slices();
+        before.iter().chain(after.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> Chain<slice::IterMut<'_, T>, slice::IterMut<'_, T>> {
+        let (before, after) = self.as_mut_slices();
+        before.iter_mut().chain(after.iter_mut())
+    }
+
+    pub fn clear(&mut self) {
+        let (before, after) = self.as_mut_slices();
+        let (before, after) = (before as *mut [T], after as *mut [T]);
+        self.gap_start = 0;
+        self.gap_end = self.buf.cap();
+        unsafe {
+            ptr::drop_in_place(before);
+            ptr::drop_in_place(after);
+        }
+    }
+}
+
+impl<T> Default for GapBuffer<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for GapBuffer<T> {
+    fn drop(&mut self) {
+        // the buffer is freed by RawVec
+        self.clear();
+    }
+}
+
+impl<T> Index<usize> for GapBuffer<T> {
+    type Output = T;
+    fn index(&self, idx: usize) -> &T {
+        self.get(idx).expect("index out of bounds")
+    }
+}
+
+impl<T> IndexMut<usize> for GapBuffer<T> {
+    fn index_mut(&mut self, idx: usize) -> &mut T {
+        self.get_mut(idx).expect("index out of bounds")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::GapBuffer;
+    use rand::random;
+    use std::rc::Rc;
+
+    fn rand_op(gap: &mut GapBuffer<i32>, std_vec: &mut Vec<i32>, cursor: &mut usize) {
+        let len = std_vec.len();
+        match random::<u32>() % 8 {
+            0..=2 => {
+                let val = random();
+                gap.insert(val);
+                std_vec.insert(*cursor, val);
+                *cursor += 1;
+            }
+            3 => {
+                let val = gap.delete_before();
+                if *cursor > 0 {
+                    *cursor -= 1;
+                    assert_eq!(val, Some(std_vec.remove(*cursor)));
+                } else {
+                    assert_eq!(val, None);
+                }
+            }
+            4 => {
+                let val = gap.delete_after();
+                if *cursor < len {
+                    assert_eq!(val, Some(std_vec.remove(*cursor)));
+                } else {
+                    assert_eq!(val, None);
+                }
+            }
+            5 => {
+                // small moves, like an editor
+                let n = random::<usize>() % 4;
+                if random::<bool>() {
+                    *cursor = cursor.saturating_sub(n);
+                } else {
+                    *cursor = std::cmp::min(*cursor + n, len);
+                }
+                gap.set_cursor(*cursor);
+            }
+            6 => {
+                *cursor = random::<usize>() % (len + 1);
+                gap.set_cursor(*cursor);
+            }
+            _ => {
+                if len > 0 {
+                    let idx = random::<usize>() % len;
+                    assert_eq!(gap[idx], std_vec[idx]);
+                    let val = random();
+                    gap[idx] = val;
+                    std_vec[idx] = val;
+                }
+                assert_eq!(gap.get(len), None);
+            }
+        }
+
+        assert_eq!(gap.len(), std_vec.len());
+        assert_eq!(gap.cursor(), *cursor);
+        let (before, after) = gap.as_slices();
+        assert_eq!(before, &std_vec[..*cursor]);
+        assert_eq!(after, &std_vec[*cursor..]);
+    }
+
+    #[test]
+    fn test() {
+        for cap in [0, 1, 16] {
+            let mut gap = GapBuffer::with_capacity(cap);
+            let mut std_vec = Vec::new();
+            let mut cursor = 0;
+            for _ in 0..10000 {
+                rand_op(&mut gap, &mut std_vec, &mut cursor);
+            }
+            assert!(gap.iter().eq(std_vec.iter()));
+            assert_eq!(gap.make_contiguous(), &std_vec[..]);
+        }
+    }
+
+    #[test]
+    fn edit() {
+        let mut gap = GapBuffer::new();
+        "hello world".chars().for_each(|c| gap.insert(c));
+        gap.move_left(5);
+        gap.delete_before();
+        ",".chars().for_each(|c| gap.insert(c));
+        gap.set_cursor(gap.len());
+        "!".chars().for_each(|c| gap.insert(c));
+        assert_eq!(gap.iter().collect::<String>(), "hello,world!");
+        assert_eq!(gap.remove(0), 'h');
+        gap.insert_at(0, 'H');
+        gap.iter_mut().for_each(|c| *c = c.to_ascii_uppercase());
+        assert_eq!(
+            gap.make_contiguous().iter().collect::<String>(),
+            "HELLO,WORLD!"
+        );
+    }
+
+    #[test]
+    fn zst_and_drop() {
+        let mut gap = GapBuffer::new();
+        for _ in 0..100 {
+            gap.insert(());
+        }
+        gap.move_left(50);
+        gap.delete_after();
+        gap.delete_before();
+        assert_eq!(gap.len(), 98);
+        assert_eq!(gap.cursor(), 49);
+
+        let rc = Rc::new(());
+        let mut gap = GapBuffer::new();
+        for _ in 0..10 {
+            gap.insert(rc.clone());
+        }
+        gap.move_left(3);
+        gap.delete_after();
+        assert_eq!(Rc::strong_count(&rc), 10);
+        drop(gap);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+}
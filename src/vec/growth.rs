@@ -0,0 +1,190 @@
+use std::cell::Cell;
+use std::cmp;
+
+/// Decides how much RawVec grows when it runs out of room.
+pub trait GrowthPolicy {
+    // new capacity when `required` elements don't fit in `cap`, RawVec takes
+    // the max with `required` so returning less is not an error
+    fn grow(&self, cap: usize, required: usize, elem_size: usize) -> usize;
+
+    // called after every (re)allocation of the buffer, for instrumentation
+    fn on_realloc(&self, _old_cap: usize, _new_cap: usize, _elem_size: usize) {}
+}
+
+// 2x, the fewest reallocations but up to half of the buffer is unused
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Doubling;
+
+impl GrowthPolicy for Doubling {
+    fn grow(&self, cap: usize, required: usize, _elem_size: usize) -> usize {
+        cmp::max(cap.saturating_mul(2), required)
+    }
+}
+
+// 1.5x, trades more reallocations for less slack
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OneAndHalf;
+
+impl GrowthPolicy for OneAndHalf {
+    fn grow(&self, cap: usize, required: usize, _elem_size: usize) -> usize {
+        // start at 4 so small vectors don't realloc on every push
+        cmp::max(cap.saturating_add(cap / 2), cmp::max(required, 4))
+    }
+}
+
+// no slack at all, every push past the capacity reallocates
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Exact;
+
+impl GrowthPolicy for Exact {
+    fn grow(&self, _cap: usize, required: usize, _elem_size: usize) -> usize {
+        required
+    }
+}
+
+// Round what the inner policy asks for up to whole pages, so large buffers
+// don't leave a partial page unused.
+#[derive(Clone, Copy, Debug)]
+pub struct PageRounded<G: GrowthPolicy = Doubling> {
+    inner: G,
+    page_size: usize,
+}
+
+impl PageRounded {
+    pub fn new() -> Self {
+        Self::with_inner(Doubling, 4096)
+    }
+}
+
+impl Default for PageRounded {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<G: GrowthPolicy> PageRounded<G> {
+    pub fn with_inner(inner: G, page_size: usize) -> Self {
+        assert!(
+            page_size.is_power_of_two(),
+            "page size must be a power of two"
+        );
+        Self { inner, page_size }
+    }
+}
+
+impl<G: GrowthPolicy> GrowthPolicy for PageRounded<G> {
+    fn grow(&self, cap: usize, required: usize, elem_size: usize) -> usize {
+        let cap = self.inner.grow(cap, required, elem_size);
+        if elem_size == 0 {
+            return cap;
+        }
+        cap.checked_mul(elem_size)
+            .and_then(|bytes| bytes.checked_next_multiple_of(self.page_size))
+            .map_or(cap, |bytes| bytes / elem_size)
+    }
+
+    fn on_realloc(&self, old_cap: usize, new_cap: usize, elem_size: usize) {
+        self.inner.on_realloc(old_cap, new_cap, elem_size);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GrowthStats {
+    // reallocations of a non-empty buffer, the first allocation is not counted
+    pub reallocs: usize,
+    // bytes moved by those reallocations, an upper bound when the allocator
+    // can resize in place
+    pub bytes_copied: usize,
+    pub peak_cap: usize,
+}
+
+// Wrap another policy and keep per-vector counters, read them with
+// `vec.growth().stats()`. A clone starts with fresh counters.
+#[derive(Debug, Default)]
+pub struct Instrumented<G: GrowthPolicy = Doubling> {
+    inner: G,
+    stats: Cell<GrowthStats>,
+}
+
+impl<G: GrowthPolicy> Instrumented<G> {
+    pub fn new(inner: G) -> Self {
+        Self {
+            inner,
+            stats: Cell::new(GrowthStats::default()),
+        }
+    }
+
+    pub fn stats(&self) -> GrowthStats {
+        self.stats.get()
+    }
+
+    pub fn reset(&self) {
+        self.stats.set(GrowthStats::default());
+    }
+}
+
+impl<G: GrowthPolicy + Clone> Clone for Instrumented<G> {
+    fn clone(&self) -> Self {
+        Self::new(self.inner.clone())
+    }
+}
+
+impl<G: GrowthPolicy> GrowthPolicy for Instrumented<G> {
+    fn grow(&self, cap: usize, required: usize, elem_size: usize) -> usize {
+        self.inner.grow(cap, required, elem_size)
+    }
+
+    fn on_realloc(&self, old_cap: usize, new_cap: usize, elem_size: usize) {
+        self.inner.on_realloc(old_cap, new_cap, elem_size);
+        let mut stats = self.stats.get();
+        if old_cap != 0 && new_cap != 0 {
+            stats.reallocs += 1;
+            stats.bytes_copied += cmp::min(old_cap, new_cap) * elem_size;
+        }
+        stats.peak_cap = cmp::max(stats.peak_cap, new_cap);
+        self.stats.set(stats);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn policies() {
+        assert_eq!(Doubling.grow(8, 9, 4), 16);
+        assert_eq!(Doubling.grow(0, 1, 4), 1);
+        assert_eq!(Doubling.grow(usize::MAX / 2 + 1, usize::MAX, 0), usize::MAX);
+        assert_eq!(OneAndHalf.grow(8, 9, 4), 12);
+        assert_eq!(OneAndHalf.grow(0, 1, 4), 4);
+        assert_eq!(Exact.grow(8, 9, 4), 9);
+
+        let page = PageRounded::new();
+        assert_eq!(page.grow(0, 1, 8), 512);
+        assert_eq!(page.grow(512, 513, 8), 1024);
+        assert_eq!(page.grow(0, 1, 0), 1);
+        // elements that don't divide the page leave less than one element
+        assert_eq!(page.grow(0, 1, 24), 170);
+        let exact = PageRounded::with_inner(Exact, 64);
+        assert_eq!(exact.grow(0, 9, 8), 16);
+    }
+
+    #[test]
+    fn instrumented() {
+        let policy = Instrumented::new(Doubling);
+        policy.on_realloc(0, 4, 8);
+        policy.on_realloc(4, 8, 8);
+        policy.on_realloc(8, 2, 8);
+        assert_eq!(
+            policy.stats(),
+            GrowthStats {
+                reallocs: 2,
+                bytes_copied: 48,
+                peak_cap: 8,
+            }
+        );
+        assert_eq!(policy.clone().stats(), GrowthStats::default());
+        policy.reset();
+        assert_eq!(policy.stats(), GrowthStats::default());
+    }
+}
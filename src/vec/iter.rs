@@ -0,0 +1,500 @@
+use super::alloc::{Allocator, Global};
+use super::growth::{Doubling, GrowthPolicy};
+use super::{RawVec, Vec};
+use std::iter::FusedIterator;
+use std::mem::ManuallyDrop;
+use std::ops::{Bound, RangeBounds};
+use std::ptr;
+use std::slice;
+
+// resolve a range against len, panic if it is out of bounds
+pub(super) fn slice_range(range: impl RangeBounds<usize>, len: usize) -> (usize, usize) {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start.checked_add(1).expect("range start overflow"),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end.checked_add(1).expect("range end overflow"),
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => len,
+    };
+    assert!(start <= end, "range start {} > end {}", start, end);
+    assert!(end <= len, "range end {} out of bounds {}", end, len);
+    (start, end)
+}
+
+// owned iterator, it takes over the buffer of the vector
+pub struct IntoIter<T, A: Allocator = Global, G: GrowthPolicy = Doubling> {
+    buf: RawVec<T, A, G>,
+    // [start, end) are not yet yielded
+    start: usize,
+    end: usize,
+}
+
+impl<T, A: Allocator, G: GrowthPolicy> IntoIter<T, A, G> {
+    pub(super) fn new(vec: Vec<T, A, G>) -> Self {
+        let vec = ManuallyDrop::new(vec);
+        let end = vec.len;
+        Self {
+            buf: unsafe { ptr::read(&vec.data) },
+            start: 0,
+            end,
+        }
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.buf.as_ptr().add(self.start), self.end - self.start) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe {
+            slice::from_raw_parts_mut(self.buf.as_ptr().add(self.start), self.end - self.start)
+        }
+    }
+}
+
+impl<T, A: Allocator, G: GrowthPolicy> Iterator for IntoIter<T, A, G> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.start == self.end {
+            None
+        } else {
+            self.start += 1;
+            unsafe { Some(ptr::read(self.buf.as_ptr().add(self.start - 1))) }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.start;
+        (len, Some(len))
+    }
+}
+
+impl<T, A: Allocator, G: GrowthPolicy> DoubleEndedIterator for IntoIter<T, A, G> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.start == self.end {
+            None
+        } else {
+            self.end -= 1;
+            unsafe { Some(ptr::read(self.buf.as_ptr().add(self.end))) }
+        }
+    }
+}
+
+impl<T, A: Allocator, G: GrowthPolicy> ExactSizeIterator for IntoIter<T, A, G> {}
+
+impl<T, A: Allocator, G: GrowthPolicy> FusedIterator for IntoIter<T, A, G> {}
+
+impl<T, A: Allocator, G: GrowthPolicy> Drop for IntoIter<T, A, G> {
+    fn drop(&mut self) {
+        // the buffer is freed by RawVec
+        unsafe {
+            ptr::drop_in_place(self.as_mut_slice());
+        }
+    }
+}
+
+// Drain moves the drained range behind the tail before yielding anything:
+//
+//   [prefix | drained | tail] => [prefix | tail | drained]
+//
+// and vec.len only covers prefix and tail, so the vector stays intact even
+// if the Drain is leaked, only the drained elements are leaked with it.
+pub struct Drain<'a, T, A: Allocator = Global, G: GrowthPolicy = Doubling> {
+    vec: &'a mut Vec<T, A, G>,
+    // [start, end) are not yet yielded
+    start: usize,
+    end: usize,
+    // where the drained range begins in the vector
+    at: usize,
+}
+
+impl<'a, T, A: Allocator, G: GrowthPolicy> Drain<'a, T, A, G> {
+    pub(super) fn new(vec: &'a mut Vec<T, A, G>, range: impl RangeBounds<usize>) -> Self {
+        let len = vec.len;
+        let (start, end) = slice_range(range, len);
+        vec[start..].rotate_left(end - start);
+        vec.len = len - (end - start);
+        Self {
+            start: vec.len,
+            end: len,
+            vec,
+            at: start,
+        }
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.vec.as_ptr().add(self.start), self.end - self.start) }
+    }
+}
+
+impl<T, A: Allocator, G: GrowthPolicy> Iterator for Drain<'_, T, A, G> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.start == self.end {
+            None
+        } else {
+            self.start += 1;
+            unsafe { Some(ptr::read(self.vec.as_ptr().add(self.start - 1))) }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.start;
+        (len, Some(len))
+    }
+}
+
+impl<T, A: Allocator, G: GrowthPolicy> DoubleEndedIterator for Drain<'_, T, A, G> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.start == self.end {
+            None
+        } else {
+            self.end -= 1;
+            unsafe { Some(ptr::read(self.vec.as_ptr().add(self.end))) }
+        }
+    }
+}
+
+impl<T, A: Allocator, G: GrowthPolicy> ExactSizeIterator for Drain<'_, T, A, G> {}
+
+impl<T, A: Allocator, G: GrowthPolicy> FusedIterator for Drain<'_, T, A, G> {}
+
+impl<T, A: Allocator, G: GrowthPolicy> Drop for Drain<'_, T, A, G> {
+    fn drop(&mut self) {
+        let rest = ptr::slice_from_raw_parts_mut(
+            unsafe { self.vec.as_ptr().add(self.start) },
+            self.end - self.start,
+        );
+        self.start = self.end;
+        unsafe {
+            ptr::drop_in_place(rest);
+        }
+    }
+}
+
+// replace a range with the items of another iterator, the replacement happens
+// when the Splice is dropped
+pub struct Splice<'a, I: Iterator, A: Allocator = Global, G: GrowthPolicy = Doubling> {
+    drain: Drain<'a, I::Item, A, G>,
+    replace_with: I,
+}
+
+impl<'a, I: Iterator, A: Allocator, G: GrowthPolicy> Splice<'a, I, A, G> {
+    pub(super) fn new(
+        vec: &'a mut Vec<I::Item, A, G>,
+        range: impl RangeBounds<usize>,
+        replace_with: I,
+    ) -> Self {
+        Self {
+            drain: Drain::new(vec, range),
+            replace_with,
+        }
+    }
+}
+
+impl<I: Iterator, A: Allocator, G: GrowthPolicy> Iterator for Splice<'_, I, A, G> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        self.drain.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.drain.size_hint()
+    }
+}
+
+impl<I: Iterator, A: Allocator, G: GrowthPolicy> DoubleEndedIterator for Splice<'_, I, A, G> {
+    fn next_back(&mut self) -> Option<I::Item> {
+        self.drain.next_back()
+    }
+}
+
+impl<I: Iterator, A: Allocator, G: GrowthPolicy> ExactSizeIterator for Splice<'_, I, A, G> {}
+
+impl<I: Iterator, A: Allocator, G: GrowthPolicy> Drop for Splice<'_, I, A, G> {
+    fn drop(&mut self) {
+        self.drain.by_ref().for_each(drop);
+
+        // the vector is [prefix | tail] now, collect first so a panic in
+        // replace_with leaves it as it is
+        let mut items = Vec::new();
+        for item in self.replace_with.by_ref() {
+            items.push(item);
+        }
+
+        let vec = &mut *self.drain.vec;
+        let (at, len, n) = (self.drain.at, vec.len, items.len);
+        vec.reserve(len + n);
+        unsafe {
+            ptr::copy(vec.as_ptr().add(at), vec.as_ptr().add(at + n), len - at);
+            ptr::copy_nonoverlapping(items.as_ptr(), vec.as_ptr().add(at), n);
+            items.len = 0;
+        }
+        vec.len = len + n;
+    }
+}
+
+// remove and yield the elements matching pred, the order of the rest is kept
+pub struct ExtractIf<
+    'a,
+    T,
+    F: FnMut(&mut T) -> bool,
+    A: Allocator = Global,
+    G: GrowthPolicy = Doubling,
+> {
+    vec: &'a mut Vec<T, A, G>,
+    // next element to check
+    idx: usize,
+    // number of elements removed so far
+    del: usize,
+    old_len: usize,
+    pred: F,
+}
+
+impl<'a, T, F: FnMut(&mut T) -> bool, A: Allocator, G: GrowthPolicy> ExtractIf<'a, T, F, A, G> {
+    pub(super) fn new(vec: &'a mut Vec<T, A, G>, pred: F) -> Self {
+        let old_len = vec.len;
+        // elements are moved around while iterating, leak them all rather than
+        // double drop if the iterator is leaked
+        vec.len = 0;
+        Self {
+            vec,
+            idx: 0,
+            del: 0,
+            old_len,
+            pred,
+        }
+    }
+}
+
+impl<T, F: FnMut(&mut T) -> bool, A: Allocator, G: GrowthPolicy> Iterator
+    for ExtractIf<'_, T, F, A, G>
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        while self.idx < self.old_len {
+            let ptr = unsafe { self.vec.as_ptr().add(self.idx) };
+            let extract = (self.pred)(unsafe { &mut *ptr });
+            self.idx += 1;
+            if extract {
+                self.del += 1;
+                return unsafe { Some(ptr::read(ptr)) };
+            } else if self.del > 0 {
+                unsafe {
+                    ptr::copy_nonoverlapping(ptr, ptr.sub(self.del), 1);
+                }
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.old_len - self.idx))
+    }
+}
+
+impl<T, F: FnMut(&mut T) -> bool, A: Allocator, G: GrowthPolicy> Drop
+    for ExtractIf<'_, T, F, A, G>
+{
+    fn drop(&mut self) {
+        // keep the unchecked elements
+        unsafe {
+            let src = self.vec.as_ptr().add(self.idx);
+            ptr::copy(src, src.sub(self.del), self.old_len - self.idx);
+        }
+        self.vec.len = self.old_len - self.del;
+    }
+}
+
+// close the gap left by dedup_by, also when same_bucket panics
+pub(super) struct DedupGuard<'a, T, A: Allocator, G: GrowthPolicy> {
+    pub(super) vec: &'a mut Vec<T, A, G>,
+    pub(super) read: usize,
+    pub(super) write: usize,
+    pub(super) old_len: usize,
+}
+
+impl<T, A: Allocator, G: GrowthPolicy> Drop for DedupGuard<'_, T, A, G> {
+    fn drop(&mut self) {
+        let rest = self.old_len - self.read;
+        unsafe {
+            let ptr = self.vec.as_ptr();
+            ptr::copy(ptr.add(self.read), ptr.add(self.write), rest);
+        }
+        self.vec.len = self.write + rest;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::Vec;
+    use rand::random;
+    use std::rc::Rc;
+
+    fn gen(len: usize) -> (Vec<i32>, std::vec::Vec<i32>) {
+        let mut my_vec = Vec::new();
+        let mut std_vec = std::vec::Vec::new();
+        for _ in 0..len {
+            let val = random::<i32>() % 8;
+            my_vec.push(val);
+            std_vec.push(val);
+        }
+        (my_vec, std_vec)
+    }
+
+    fn rand_range(len: usize) -> (usize, usize) {
+        let start = random::<usize>() % (len + 1);
+        let end = start + random::<usize>() % (len - start + 1);
+        (start, end)
+    }
+
+    #[test]
+    fn into_iter() {
+        let (my_vec, std_vec) = gen(100);
+        let mut into_iter = my_vec.into_iter();
+        let mut std_iter = std_vec.into_iter();
+        while std_iter.len() > 0 {
+            assert_eq!(into_iter.as_slice(), std_iter.as_slice());
+            if random::<bool>() {
+                assert_eq!(into_iter.next(), std_iter.next());
+            } else {
+                assert_eq!(into_iter.next_back(), std_iter.next_back());
+            }
+        }
+        assert_eq!(into_iter.next(), None);
+        assert_eq!(into_iter.next_back(), None);
+
+        // the rest is dropped with the iterator
+        let rc = Rc::new(());
+        let mut rc_vec = Vec::new();
+        for _ in 0..10 {
+            rc_vec.push(rc.clone());
+        }
+        let mut into_iter = rc_vec.into_iter();
+        into_iter.next();
+        into_iter.next_back();
+        assert_eq!(Rc::strong_count(&rc), 9);
+        drop(into_iter);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn drain() {
+        for _ in 0..100 {
+            let (mut my_vec, mut std_vec) = gen(random::<usize>() % 100);
+            let (start, end) = rand_range(my_vec.len());
+            let mut drain = my_vec.drain(start..end);
+            let mut std_drain = std_vec.drain(start..end);
+            assert_eq!(drain.as_slice(), std_drain.as_slice());
+            for _ in 0..random::<usize>() % (end - start + 1) {
+                if random::<bool>() {
+                    assert_eq!(drain.next(), std_drain.next());
+                } else {
+                    assert_eq!(drain.next_back(), std_drain.next_back());
+                }
+            }
+            drop(drain);
+            drop(std_drain);
+            assert_eq!(&my_vec[..], &std_vec[..]);
+        }
+
+        let (mut my_vec, std_vec) = gen(10);
+        let drained: std::vec::Vec<_> = my_vec.drain(..).collect();
+        assert_eq!(drained, std_vec);
+        assert_eq!(my_vec.len(), 0);
+    }
+
+    #[test]
+    fn drain_leak() {
+        let rc = Rc::new(());
+        let mut rc_vec = Vec::new();
+        for i in 0..10 {
+            rc_vec.push((i, rc.clone()));
+        }
+
+        let mut drain = rc_vec.drain(2..5);
+        assert_eq!(drain.next().unwrap().0, 2);
+        std::mem::forget(drain);
+
+        // the tail is kept, two drained elements are leaked
+        let left: std::vec::Vec<_> = rc_vec.iter().map(|(i, _)| *i).collect();
+        assert_eq!(left, [0, 1, 5, 6, 7, 8, 9]);
+        drop(rc_vec);
+        assert_eq!(Rc::strong_count(&rc), 3);
+    }
+
+    #[test]
+    fn splice() {
+        for _ in 0..100 {
+            let (mut my_vec, mut std_vec) = gen(random::<usize>() % 100);
+            let (start, end) = rand_range(my_vec.len());
+            let replace_with: std::vec::Vec<i32> =
+                (0..random::<usize>() % 20).map(|_| random()).collect();
+
+            let removed: std::vec::Vec<_> =
+                my_vec.splice(start..end, replace_with.clone()).collect();
+            let std_removed: std::vec::Vec<_> = std_vec.splice(start..end, replace_with).collect();
+            assert_eq!(removed, std_removed);
+            assert_eq!(&my_vec[..], &std_vec[..]);
+        }
+
+        // replace without consuming the removed elements
+        let (mut my_vec, mut std_vec) = gen(10);
+        drop(my_vec.splice(3..=4, vec![100, 101, 102]));
+        drop(std_vec.splice(3..=4, vec![100, 101, 102]));
+        assert_eq!(&my_vec[..], &std_vec[..]);
+    }
+
+    #[test]
+    fn extract_if() {
+        for _ in 0..100 {
+            let (mut my_vec, mut std_vec) = gen(random::<usize>() % 100);
+            let extracted: std::vec::Vec<_> = my_vec.extract_if(|x| *x % 2 == 0).collect();
+            let std_extracted: std::vec::Vec<_> =
+                std_vec.iter().copied().filter(|x| *x % 2 == 0).collect();
+            std_vec.retain(|x| *x % 2 != 0);
+            assert_eq!(extracted, std_extracted);
+            assert_eq!(&my_vec[..], &std_vec[..]);
+        }
+
+        // stop early, the unchecked elements are kept
+        let mut my_vec = Vec::new();
+        for i in 0..10 {
+            my_vec.push(i);
+        }
+        let mut extract = my_vec.extract_if(|x| *x % 3 == 0);
+        assert_eq!(extract.next(), Some(0));
+        assert_eq!(extract.next(), Some(3));
+        drop(extract);
+        assert_eq!(&my_vec[..], &[1, 2, 4, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn retain_dedup() {
+        for _ in 0..100 {
+            let (mut my_vec, mut std_vec) = gen(random::<usize>() % 100);
+            my_vec.retain(|x| *x > 1);
+            std_vec.retain(|x| *x > 1);
+            assert_eq!(&my_vec[..], &std_vec[..]);
+            my_vec.dedup();
+            std_vec.dedup();
+            assert_eq!(&my_vec[..], &std_vec[..]);
+        }
+
+        let rc = Rc::new(());
+        let mut rc_vec = Vec::new();
+        for i in 0..10 {
+            rc_vec.push((i / 3, rc.clone()));
+        }
+        rc_vec.dedup_by_key(|(i, _)| *i);
+        let left: std::vec::Vec<_> = rc_vec.iter().map(|(i, _)| *i).collect();
+        assert_eq!(left, [0, 1, 2, 3]);
+        assert_eq!(Rc::strong_count(&rc), 5);
+    }
+}
@@ -0,0 +1,435 @@
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::ptr;
+use std::slice;
+
+const MAGIC: [u8; 8] = *b"MMAPVEC1";
+// the data starts after the header, so T may be aligned up to this
+const HEADER_SIZE: usize = 64;
+const PAGE_SIZE: usize = 4096;
+
+#[repr(C)]
+struct Header {
+    magic: [u8; 8],
+    elem_size: u64,
+    elem_align: u64,
+    // kept up to date on every change, so a crashed process can reopen it
+    len: u64,
+}
+
+// Vector stored in a file: [header][elements][unused capacity]. The whole
+// file is mapped shared, growing extends the file and maps it again.
+pub struct MmapVec<T: Copy> {
+    file: File,
+    // start of the mapping, i.e. the header
+    map: *mut u8,
+    cap: usize,
+    len: usize,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Copy> MmapVec<T> {
+    // create an empty vector, truncating the file if it exists
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::with_capacity(path, 0)
+    }
+
+    pub fn with_capacity(path: impl AsRef<Path>, cap: usize) -> io::Result<Self> {
+        assert!(
+            mem::align_of::<T>() <= HEADER_SIZE,
+            "alignment of T is too large"
+        );
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        let mut vec = Self {
+            map: Self::map(&file, 0)?,
+            file,
+            cap: 0,
+            len: 0,
+            _marker: std::marker::PhantomData,
+        };
+        unsafe {
+            ptr::write(
+                vec.map as *mut Header,
+                Header {
+                    magic: MAGIC,
+                    elem_size: mem::size_of::<T>() as u64,
+                    elem_align: mem::align_of::<T>() as u64,
+                    len: 0,
+                },
+            );
+        }
+        vec.try_reserve(cap)?;
+        Ok(vec)
+    }
+
+    /// Reopen a file written by MmapVec::<T>.
+    ///
+    /// # Safety
+    ///
+    /// Only the size and alignment of T are checked, the file must have been
+    /// written with the same T, or any bit pattern must be valid for T.
+    pub unsafe fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let file_size = file.metadata()?.len() as usize;
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+        if file_size < HEADER_SIZE {
+            return Err(invalid("file too small"));
+        }
+
+        // a partial element at the end is left out, so the mapping is always
+        // map_size long
+        let cap = Self::cap_of(file_size);
+        let map = Self::map(&file, cap * mem::size_of::<T>())?;
+        let mut vec = Self {
+            file,
+            map,
+            cap,
+            len: 0,
+            _marker: std::marker::PhantomData,
+        };
+        let header = &*(vec.map as *const Header);
+        if header.magic != MAGIC {
+            return Err(invalid("not a MmapVec file"));
+        }
+        if header.elem_size != mem::size_of::<T>() as u64
+            || header.elem_align != mem::align_of::<T>() as u64
+        {
+            return Err(invalid("element type mismatch"));
+        }
+        if header.len > vec.capacity() as u64 {
+            return Err(invalid("length past the end of file"));
+        }
+        vec.len = header.len as usize;
+        Ok(vec)
+    }
+
+    fn cap_of(file_size: usize) -> usize {
+        match mem::size_of::<T>() {
+            0 => 0,
+            size => (file_size - HEADER_SIZE) / size,
+        }
+    }
+
+    // map the header and `data_bytes` after it
+    fn map(file: &File, data_bytes: usize) -> io::Result<*mut u8> {
+        let size = HEADER_SIZE + data_bytes;
+        if file.metadata()?.len() < size as u64 {
+            file.set_len(size as u64)?;
+        }
+        let map = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if map == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(map as *mut u8)
+    }
+
+    fn map_size(&self) -> usize {
+        HEADER_SIZE + self.cap * mem::size_of::<T>()
+    }
+
+    fn unmap(&mut self) {
+        unsafe {
+            libc::munmap(self.map as _, self.map_size());
+        }
+    }
+
+    fn as_ptr(&self) -> *mut T {
+        if mem::size_of::<T>() == 0 {
+            ptr::NonNull::dangling().as_ptr()
+        } else {
+            unsafe { self.map.add(HEADER_SIZE) as *mut T }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        if mem::size_of::<T>() == 0 {
+            usize::MAX
+        } else {
+            self.cap
+        }
+    }
+
+    unsafe fn set_len(&mut self, len: usize) {
+        self.len = len;
+        (*(self.map as *mut Header)).len = len as u64;
+    }
+
+    pub fn reserve(&mut self, target_cap: usize) {
+        self.try_reserve(target_cap)
+            .expect("failed to grow the file");
+    }
+
+    // grow the file to hold target_cap elements and map it again
+    pub fn try_reserve(&mut self, target_cap: usize) -> io::Result<()> {
+        if target_cap <= self.capacity() {
+            return Ok(());
+        }
+
+        let data_bytes = target_cap
+            .checked_mul(mem::size_of::<T>())
+            .filter(|bytes| *bytes <= isize::MAX as usize - HEADER_SIZE)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "capacity overflow"))?;
+        let map = Self::map(&self.file, data_bytes)?;
+        self.unmap();
+        self.map = map;
+        self.cap = target_cap;
+        Ok(())
+    }
+
+    fn grow_one(&mut self) -> io::Result<()> {
+        if self.len == self.capacity() {
+            let target_cap = self
+                .len
+                .checked_add(1)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "capacity overflow"))?;
+            // fill at least the first page
+            let first_page = (PAGE_SIZE - HEADER_SIZE) / mem::size_of::<T>();
+            self.try_reserve(std::cmp::max(
+                std::cmp::max(2 * self.len, target_cap),
+                first_page,
+            ))?;
+        }
+        Ok(())
+    }
+
+    pub fn push(&mut self, val: T) {
+        self.try_push(val).expect("failed to grow the file");
+    }
+
+    pub fn try_push(&mut self, val: T) -> io::Result<()> {
+        self.grow_one()?;
+        unsafe {
+            ptr::write(self.as_ptr().add(self.len), val);
+            self.set_len(self.len + 1);
+        }
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            unsafe {
+                self.set_len(self.len - 1);
+                Some(ptr::read(self.as_ptr().add(self.len)))
+            }
+        }
+    }
+
+    pub fn extend_from_slice(&mut self, other: &[T]) {
+        let target_cap = self
+            .len
+            .checked_add(other.len())
+            .expect("capacity overflow");
+        if target_cap > self.capacity() {
+            self.reserve(std::cmp::max(2 * self.len, target_cap));
+        }
+        unsafe {
+            ptr::copy_nonoverlapping(other.as_ptr(), self.as_ptr().add(self.len), other.len());
+            self.set_len(target_cap);
+        }
+    }
+
+    // T is Copy, nothing to drop
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len {
+            unsafe {
+                self.set_len(len);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    // shrink the file to the elements in use
+    pub fn shrink_to_fit(&mut self) -> io::Result<()> {
+        if self.cap == self.len || mem::size_of::<T>() == 0 {
+            return Ok(());
+        }
+
+        // map the smaller size first, a failed truncate only wastes space
+        let data_bytes = self.len * mem::size_of::<T>();
+        let map = Self::map(&self.file, data_bytes)?;
+        self.unmap();
+        self.map = map;
+        self.cap = self.len;
+        self.file.set_len((HEADER_SIZE + data_bytes) as u64)
+    }
+
+    // write the mapping back to the file
+    pub fn flush(&self) -> io::Result<()> {
+        let ret = unsafe { libc::msync(self.map as _, self.map_size(), libc::MS_SYNC) };
+        if ret != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl<T: Copy> Drop for MmapVec<T> {
+    fn drop(&mut self) {
+        // the kernel writes back shared mappings, no need to flush
+        self.unmap();
+    }
+}
+
+impl<T: Copy> Deref for MmapVec<T> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.as_ptr(), self.len) }
+    }
+}
+
+impl<T: Copy> DerefMut for MmapVec<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.as_ptr(), self.len) }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MmapVec;
+    use rand::random;
+    use std::path::PathBuf;
+
+    // removes the file at the end of the test
+    struct TempPath(PathBuf);
+
+    impl TempPath {
+        fn new() -> Self {
+            let name = format!("mmapvec-{}-{}", std::process::id(), random::<u64>());
+            Self(std::env::temp_dir().join(name))
+        }
+    }
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn test() {
+        let path = TempPath::new();
+        let mut std_vec = Vec::new();
+        {
+            let mut my_vec = MmapVec::create(&path.0).unwrap();
+            for _ in 0..10000 {
+                match random::<u32>() % 4 {
+                    0 => assert_eq!(my_vec.pop(), std_vec.pop()),
+                    1 if !std_vec.is_empty() => {
+                        let idx = random::<usize>() % std_vec.len();
+                        let val = random::<u64>();
+                        my_vec[idx] = val;
+                        std_vec[idx] = val;
+                    }
+                    _ => {
+                        let val = random::<u64>();
+                        my_vec.push(val);
+                        std_vec.push(val);
+                    }
+                }
+            }
+            my_vec.extend_from_slice(&[1, 2, 3]);
+            std_vec.extend_from_slice(&[1, 2, 3]);
+            assert_eq!(&my_vec[..], &std_vec[..]);
+            my_vec.flush().unwrap();
+        }
+
+        let mut my_vec = unsafe { MmapVec::<u64>::open(&path.0).unwrap() };
+        assert_eq!(&my_vec[..], &std_vec[..]);
+        my_vec.truncate(100);
+        my_vec.shrink_to_fit().unwrap();
+        assert_eq!(my_vec.capacity(), 100);
+        my_vec.push(42);
+        drop(my_vec);
+
+        let my_vec = unsafe { MmapVec::<u64>::open(&path.0).unwrap() };
+        assert_eq!(&my_vec[..100], &std_vec[..100]);
+        assert_eq!(my_vec[100], 42);
+    }
+
+    #[test]
+    fn open_errors() {
+        let path = TempPath::new();
+        let mut my_vec = MmapVec::<u32>::with_capacity(&path.0, 10).unwrap();
+        my_vec.push(1);
+        drop(my_vec);
+        unsafe {
+            assert!(MmapVec::<u64>::open(&path.0).is_err());
+            assert!(MmapVec::<u32>::open(&path.0).is_ok());
+        }
+
+        std::fs::write(
+            &path.0,
+            b"not a vector, but long enough to hold a header...............",
+        )
+        .unwrap();
+        unsafe {
+            assert!(MmapVec::<u32>::open(&path.0).is_err());
+        }
+        assert!(unsafe { MmapVec::<u32>::open(path.0.join("missing")) }.is_err());
+    }
+
+    #[test]
+    fn open_partial_elem() {
+        let path = TempPath::new();
+        let mut my_vec = MmapVec::<u32>::with_capacity(&path.0, 10).unwrap();
+        my_vec.extend_from_slice(&[1, 2, 3]);
+        drop(my_vec);
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(&path.0)
+            .unwrap();
+        file.set_len(64 + 10 * 4 + 3).unwrap();
+        drop(file);
+
+        let mut my_vec = unsafe { MmapVec::<u32>::open(&path.0).unwrap() };
+        assert_eq!(my_vec.capacity(), 10);
+        my_vec.extend_from_slice(&[4; 10]);
+        assert_eq!(&my_vec[..4], &[1, 2, 3, 4]);
+        assert_eq!(my_vec.len(), 13);
+    }
+
+    #[test]
+    fn zst() {
+        let path = TempPath::new();
+        let mut my_vec = MmapVec::create(&path.0).unwrap();
+        for _ in 0..1000 {
+            my_vec.push(());
+        }
+        assert_eq!(my_vec.pop(), Some(()));
+        drop(my_vec);
+        let my_vec = unsafe { MmapVec::<()>::open(&path.0).unwrap() };
+        assert_eq!(my_vec.len(), 999);
+        assert_eq!(std::fs::metadata(&path.0).unwrap().len(), 64);
+    }
+}
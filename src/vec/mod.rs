@@ -0,0 +1,1288 @@
+// like std vec!, but builds the Vec of this module
+#[allow(unused_macros)]
+macro_rules! vector {
+    () => {
+        $crate::vec::Vec::new()
+    };
+    ($elem:expr; $n:expr) => {
+        $crate::vec::from_elem($elem, $n)
+    };
+    ($($x:expr),+ $(,)?) => {
+        $crate::vec::Vec::from([$($x),+])
+    };
+}
+
+mod alloc;
+mod arena;
+mod bitvec;
+mod concurrent;
+mod deque;
+mod gap;
+mod growth;
+mod iter;
+#[cfg(unix)]
+mod mmap;
+mod persistent;
+mod smallvec;
+
+use alloc::{Aligned, Allocator, Global};
+use iter::{DedupGuard, Drain, ExtractIf, IntoIter, Splice};
+use std::alloc::{handle_alloc_error, Layout};
+use std::borrow::{Borrow, BorrowMut};
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+use std::mem::{self, ManuallyDrop};
+use std::ops::{Deref, DerefMut, RangeBounds};
+use std::ptr;
+use std::slice;
+
+// re-exported by lib.rs for examples/growth.rs
+pub use growth::{
+    Doubling, Exact, GrowthPolicy, GrowthStats, Instrumented, OneAndHalf, PageRounded,
+};
+
+pub struct Vec<T, A: Allocator = Global, G: GrowthPolicy = Doubling> {
+    data: RawVec<T, A, G>,
+    len: usize,
+}
+
+// Vec whose buffer is aligned to at least ALIGN bytes, create it with
+// Vec::new_in(Aligned::new()). An empty vector has a dangling pointer that is
+// only aligned for T.
+type AlignedVec<T, const ALIGN: usize> = Vec<T, Aligned<Global, ALIGN>>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TryReserveError {
+    // the capacity does not fit in a Layout (more than isize::MAX bytes)
+    CapacityOverflow,
+    // the allocator returned null
+    AllocError { layout: Layout },
+}
+
+impl fmt::Display for TryReserveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryReserveError::CapacityOverflow => write!(f, "capacity overflow"),
+            TryReserveError::AllocError { layout } => {
+                write!(f, "failed to allocate {} bytes", layout.size())
+            }
+        }
+    }
+}
+
+impl Error for TryReserveError {}
+
+// turn a reserve error into panic or abort, for the infallible apis
+fn handle_reserve<T>(result: Result<T, TryReserveError>) -> T {
+    match result {
+        Ok(val) => val,
+        Err(TryReserveError::CapacityOverflow) => panic!("capacity overflow"),
+        Err(TryReserveError::AllocError { layout }) => handle_alloc_error(layout),
+    }
+}
+
+struct Unique<T> {
+    ptr: *const T,
+    _marker: PhantomData<T>,
+}
+
+// owns its T like a Box, so it is Send/Sync when T is
+unsafe impl<T: Send> Send for Unique<T> {}
+unsafe impl<T: Sync> Sync for Unique<T> {}
+
+impl<T> Unique<T> {
+    fn dangling() -> Self {
+        Self::new_unchecked(ptr::NonNull::dangling().as_ptr())
+    }
+
+    fn new_unchecked(ptr: *mut T) -> Self {
+        Self {
+            ptr: ptr as _,
+            _marker: PhantomData,
+        }
+    }
+
+    fn as_ptr(&self) -> *mut T {
+        self.ptr as _
+    }
+}
+
+struct RawVec<T, A: Allocator = Global, G: GrowthPolicy = Doubling> {
+    ptr: Unique<T>,
+    cap: usize,
+    alloc: A,
+    growth: G,
+}
+
+impl<T> RawVec<T> {
+    fn new() -> Self {
+        Self::new_in(Global)
+    }
+
+    fn with_capacity(cap: usize) -> Self {
+        Self::with_capacity_in(cap, Global)
+    }
+
+    // give up the buffer without freeing it, from_raw_parts takes it back
+    fn into_raw(self) -> *mut T {
+        ManuallyDrop::new(self).as_ptr()
+    }
+
+    /// # Safety
+    ///
+    /// ptr and cap must come from into_raw of a RawVec with this capacity.
+    unsafe fn from_raw_parts(ptr: *mut T, cap: usize) -> Self {
+        Self {
+            ptr: Unique::new_unchecked(ptr),
+            cap,
+            alloc: Global,
+            growth: Doubling,
+        }
+    }
+}
+
+impl<T, A: Allocator> RawVec<T, A> {
+    fn new_in(alloc: A) -> Self {
+        Self::with_growth_in(alloc, Doubling)
+    }
+
+    fn with_capacity_in(cap: usize, alloc: A) -> Self {
+        handle_reserve(Self::try_with_capacity_in(cap, alloc))
+    }
+
+    fn try_with_capacity_in(cap: usize, alloc: A) -> Result<Self, TryReserveError> {
+        let mut raw = Self::new_in(alloc);
+        raw.try_reserve(cap)?;
+        Ok(raw)
+    }
+}
+
+impl<T, A: Allocator, G: GrowthPolicy> RawVec<T, A, G> {
+    fn with_growth_in(alloc: A, growth: G) -> Self {
+        Self {
+            ptr: Unique::dangling(),
+            cap: 0,
+            alloc,
+            growth,
+        }
+    }
+
+    fn cap(&self) -> usize {
+        if mem::size_of::<T>() == 0 {
+            usize::MAX
+        } else {
+            self.cap
+        }
+    }
+
+    fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    fn growth(&self) -> &G {
+        &self.growth
+    }
+
+    fn reserve(&mut self, target_cap: usize) {
+        handle_reserve(self.try_reserve(target_cap));
+    }
+
+    // make room for at least `required` elements, the policy picks how much
+    fn try_grow(&mut self, required: usize) -> Result<(), TryReserveError> {
+        if required <= self.cap() {
+            return Ok(());
+        }
+        let target_cap = self.growth.grow(self.cap, required, mem::size_of::<T>());
+        self.try_reserve(std::cmp::max(target_cap, required))
+    }
+
+    fn try_reserve(&mut self, target_cap: usize) -> Result<(), TryReserveError> {
+        // never allocate for ZST, cap() is usize::MAX
+        if target_cap <= self.cap() {
+            return Ok(());
+        }
+
+        let layout =
+            Layout::array::<T>(target_cap).map_err(|_| TryReserveError::CapacityOverflow)?;
+        let ptr = if self.cap == 0 {
+            unsafe { self.alloc.alloc(layout) }
+        } else {
+            let old_layout = Layout::array::<T>(self.cap).unwrap();
+            unsafe {
+                self.alloc
+                    .realloc(self.as_ptr() as _, old_layout, layout.size())
+            }
+        };
+        if ptr.is_null() {
+            return Err(TryReserveError::AllocError { layout });
+        }
+
+        self.growth
+            .on_realloc(self.cap, target_cap, mem::size_of::<T>());
+        self.ptr = Unique::new_unchecked(ptr as _);
+        self.cap = target_cap;
+        Ok(())
+    }
+
+    fn as_ptr(&self) -> *mut T {
+        self.ptr.as_ptr()
+    }
+
+    fn shrink(&mut self, target_cap: usize) {
+        assert!(target_cap <= self.cap(), "shrink to a larger capacity");
+        if mem::size_of::<T>() == 0 || target_cap == self.cap {
+            return;
+        }
+
+        let old_layout = Layout::array::<T>(self.cap).unwrap();
+        if target_cap == 0 {
+            unsafe {
+                self.alloc.dealloc(self.as_ptr() as _, old_layout);
+            }
+            self.ptr = Unique::dangling();
+        } else {
+            let layout = Layout::array::<T>(target_cap).unwrap();
+            let ptr = unsafe {
+                self.alloc
+                    .realloc(self.as_ptr() as _, old_layout, layout.size())
+            };
+            if ptr.is_null() {
+                handle_alloc_error(layout);
+            }
+
+            self.ptr = Unique::new_unchecked(ptr as _);
+        }
+        self.growth
+            .on_realloc(self.cap, target_cap, mem::size_of::<T>());
+        self.cap = target_cap;
+    }
+}
+
+impl<T, A: Allocator, G: GrowthPolicy> Drop for RawVec<T, A, G> {
+    fn drop(&mut self) {
+        // todo: add may dangle
+        if mem::size_of::<T>() != 0 && self.cap > 0 {
+            unsafe {
+                self.alloc.dealloc(
+                    self.ptr.as_ptr() as _,
+                    Layout::array::<T>(self.cap).unwrap(),
+                );
+            }
+        }
+    }
+}
+
+impl<T> Vec<T> {
+    fn new() -> Self {
+        Self::new_in(Global)
+    }
+
+    fn with_capacity(cap: usize) -> Self {
+        Self::with_capacity_in(cap, Global)
+    }
+
+    fn try_with_capacity(cap: usize) -> Result<Self, TryReserveError> {
+        Self::try_with_capacity_in(cap, Global)
+    }
+}
+
+impl<T, G: GrowthPolicy> Vec<T, Global, G> {
+    pub fn with_growth(growth: G) -> Self {
+        Self::with_growth_in(Global, growth)
+    }
+}
+
+impl<T, A: Allocator> Vec<T, A> {
+    fn new_in(alloc: A) -> Self {
+        Self {
+            data: RawVec::new_in(alloc),
+            len: 0,
+        }
+    }
+
+    fn with_capacity_in(cap: usize, alloc: A) -> Self {
+        Self {
+            data: RawVec::with_capacity_in(cap, alloc),
+            len: 0,
+        }
+    }
+
+    fn try_with_capacity_in(cap: usize, alloc: A) -> Result<Self, TryReserveError> {
+        Ok(Self {
+            data: RawVec::try_with_capacity_in(cap, alloc)?,
+            len: 0,
+        })
+    }
+}
+
+impl<T, A: Allocator, G: GrowthPolicy> Vec<T, A, G> {
+    fn with_growth_in(alloc: A, growth: G) -> Self {
+        Self {
+            data: RawVec::with_growth_in(alloc, growth),
+            len: 0,
+        }
+    }
+
+    fn allocator(&self) -> &A {
+        self.data.allocator()
+    }
+
+    pub fn growth(&self) -> &G {
+        self.data.growth()
+    }
+
+    fn as_ptr(&self) -> *mut T {
+        self.data.as_ptr()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.data.cap()
+    }
+
+    fn reserve(&mut self, cap: usize) {
+        self.data.reserve(cap);
+    }
+
+    // unlike reserve, the try_* apis take the number of additional elements
+    fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        let target_cap = self
+            .len
+            .checked_add(additional)
+            .ok_or(TryReserveError::CapacityOverflow)?;
+        self.data.try_grow(target_cap)
+    }
+
+    fn try_reserve_exact(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        let target_cap = self
+            .len
+            .checked_add(additional)
+            .ok_or(TryReserveError::CapacityOverflow)?;
+        self.data.try_reserve(target_cap)
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.data.shrink(self.len);
+    }
+
+    // shrink the capacity to max(len, min_cap), do nothing if it is already lower
+    fn shrink_to(&mut self, min_cap: usize) {
+        let target_cap = std::cmp::max(self.len, min_cap);
+        if target_cap < self.data.cap() {
+            self.data.shrink(target_cap);
+        }
+    }
+
+    fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+
+        // set len first, so a panic in drop will not cause a double drop
+        let tail = ptr::slice_from_raw_parts_mut(unsafe { self.as_ptr().add(len) }, self.len - len);
+        self.len = len;
+        unsafe {
+            ptr::drop_in_place(tail);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    pub fn push(&mut self, val: T) {
+        handle_reserve(self.try_push(val));
+    }
+
+    // make room for one more element
+    fn try_grow_one(&mut self) -> Result<(), TryReserveError> {
+        if self.len == self.data.cap() {
+            // only ZST can get here with len == usize::MAX
+            let target_cap = self
+                .len
+                .checked_add(1)
+                .ok_or(TryReserveError::CapacityOverflow)?;
+            self.data.try_grow(target_cap)?;
+        }
+        Ok(())
+    }
+
+    fn try_push(&mut self, val: T) -> Result<(), TryReserveError> {
+        self.try_grow_one()?;
+        unsafe {
+            ptr::write(self.as_ptr().add(self.len), val);
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    fn insert(&mut self, idx: usize, val: T) {
+        assert!(idx <= self.len, "insert index out of bounds");
+        handle_reserve(self.try_grow_one());
+
+        // this can be optimized...
+        unsafe {
+            ptr::copy(
+                self.as_ptr().add(idx),
+                self.as_ptr().add(idx + 1),
+                self.len - idx,
+            );
+            ptr::write(self.as_ptr().add(idx), val);
+        }
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            self.len -= 1;
+            unsafe { Some(ptr::read(self.as_ptr().add(self.len))) }
+        }
+    }
+
+    fn remove(&mut self, idx: usize) {
+        unsafe {
+            ptr::drop_in_place(self.as_ptr().add(idx));
+            ptr::copy(
+                self.as_ptr().add(idx + 1),
+                self.as_ptr().add(idx),
+                self.len - idx - 1,
+            );
+        }
+        self.len -= 1;
+    }
+
+    fn get(&self, idx: usize) -> Option<&T> {
+        if self.len <= idx {
+            None
+        } else {
+            unsafe { Some(&*self.as_ptr().add(idx)) }
+        }
+    }
+
+    fn get_mut(&mut self, idx: usize) -> Option<&mut T> {
+        // what if we map get_mut to get?
+        if self.len <= idx {
+            None
+        } else {
+            unsafe { Some(&mut *self.as_ptr().add(idx)) }
+        }
+    }
+}
+
+impl<T, A: Allocator, G: GrowthPolicy> Vec<T, A, G> {
+    fn drain(&mut self, range: impl RangeBounds<usize>) -> Drain<'_, T, A, G> {
+        Drain::new(self, range)
+    }
+
+    fn splice<I: IntoIterator<Item = T>>(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        replace_with: I,
+    ) -> Splice<'_, I::IntoIter, A, G> {
+        Splice::new(self, range, replace_with.into_iter())
+    }
+
+    fn extract_if<F: FnMut(&mut T) -> bool>(&mut self, pred: F) -> ExtractIf<'_, T, F, A, G> {
+        ExtractIf::new(self, pred)
+    }
+
+    fn retain(&mut self, mut f: impl FnMut(&T) -> bool) {
+        self.retain_mut(|elem| f(elem));
+    }
+
+    fn retain_mut(&mut self, mut f: impl FnMut(&mut T) -> bool) {
+        self.extract_if(|elem| !f(elem)).for_each(drop);
+    }
+
+    // same_bucket(a, b) is called with a after b, a is removed if it returns true
+    fn dedup_by(&mut self, mut same_bucket: impl FnMut(&mut T, &mut T) -> bool) {
+        if self.len <= 1 {
+            return;
+        }
+
+        let old_len = self.len;
+        let mut guard = DedupGuard {
+            vec: self,
+            read: 1,
+            write: 1,
+            old_len,
+        };
+        let ptr = guard.vec.as_ptr();
+        while guard.read < old_len {
+            unsafe {
+                let read = ptr.add(guard.read);
+                let prev = ptr.add(guard.write - 1);
+                if same_bucket(&mut *read, &mut *prev) {
+                    guard.read += 1;
+                    ptr::drop_in_place(read);
+                } else {
+                    ptr::copy(read, ptr.add(guard.write), 1);
+                    guard.write += 1;
+                    guard.read += 1;
+                }
+            }
+        }
+    }
+
+    fn dedup_by_key<K: PartialEq>(&mut self, mut key: impl FnMut(&mut T) -> K) {
+        self.dedup_by(|a, b| key(a) == key(b));
+    }
+}
+
+impl<T: PartialEq, A: Allocator, G: GrowthPolicy> Vec<T, A, G> {
+    fn dedup(&mut self) {
+        self.dedup_by(|a, b| a == b);
+    }
+}
+
+impl<T: Clone, A: Allocator, G: GrowthPolicy> Vec<T, A, G> {
+    fn extend_from_slice(&mut self, other: &[T]) {
+        handle_reserve(self.try_reserve(other.len()));
+        // len is bumped per element, a panicking clone leaves a valid prefix
+        for elem in other {
+            self.push(elem.clone());
+        }
+    }
+}
+
+fn from_elem<T: Clone>(elem: T, n: usize) -> Vec<T> {
+    let mut vec = Vec::with_capacity(n);
+    if n > 0 {
+        for _ in 1..n {
+            vec.push(elem.clone());
+        }
+        vec.push(elem);
+    }
+    vec
+}
+
+impl<T, A: Allocator + Clone, G: GrowthPolicy + Clone> Vec<T, A, G> {
+    fn split_off(&mut self, at: usize) -> Self {
+        assert!(at <= self.len, "split_off index out of bounds");
+        let mut other = Self::with_growth_in(self.allocator().clone(), self.growth().clone());
+        other.reserve(self.len - at);
+        unsafe {
+            ptr::copy_nonoverlapping(self.as_ptr().add(at), other.as_ptr(), self.len - at);
+        }
+        other.len = self.len - at;
+        self.len = at;
+        other
+    }
+}
+
+impl<T, A: Allocator, G: GrowthPolicy> Drop for Vec<T, A, G> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T, A: Allocator, G: GrowthPolicy> Deref for Vec<T, A, G> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.as_ptr(), self.len) }
+    }
+}
+
+impl<T, A: Allocator, G: GrowthPolicy> DerefMut for Vec<T, A, G> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.as_ptr(), self.len) }
+    }
+}
+
+impl<T, A: Allocator, G: GrowthPolicy> IntoIterator for Vec<T, A, G> {
+    type Item = T;
+    type IntoIter = IntoIter<T, A, G>;
+
+    fn into_iter(self) -> IntoIter<T, A, G> {
+        IntoIter::new(self)
+    }
+}
+
+impl<'a, T, A: Allocator, G: GrowthPolicy> IntoIterator for &'a Vec<T, A, G> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> slice::Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T, A: Allocator, G: GrowthPolicy> IntoIterator for &'a mut Vec<T, A, G> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> slice::IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+impl<T> Default for Vec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone, A: Allocator + Clone, G: GrowthPolicy + Clone> Clone for Vec<T, A, G> {
+    fn clone(&self) -> Self {
+        // if a clone panics the partial copy is dropped like any other Vec
+        let mut vec = Self::with_growth_in(self.allocator().clone(), self.growth().clone());
+        vec.reserve(self.len);
+        vec.extend_from_slice(self);
+        vec
+    }
+}
+
+impl<T: fmt::Debug, A: Allocator, G: GrowthPolicy> fmt::Debug for Vec<T, A, G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T, U, A: Allocator, G: GrowthPolicy, B: Allocator, H: GrowthPolicy> PartialEq<Vec<U, B, H>>
+    for Vec<T, A, G>
+where
+    T: PartialEq<U>,
+{
+    fn eq(&self, other: &Vec<U, B, H>) -> bool {
+        self[..] == other[..]
+    }
+}
+
+impl<T: PartialEq<U>, U, A: Allocator, G: GrowthPolicy> PartialEq<[U]> for Vec<T, A, G> {
+    fn eq(&self, other: &[U]) -> bool {
+        self[..] == other[..]
+    }
+}
+
+impl<T: PartialEq<U>, U, A: Allocator, G: GrowthPolicy, const N: usize> PartialEq<[U; N]>
+    for Vec<T, A, G>
+{
+    fn eq(&self, other: &[U; N]) -> bool {
+        self[..] == other[..]
+    }
+}
+
+impl<T: Eq, A: Allocator, G: GrowthPolicy> Eq for Vec<T, A, G> {}
+
+impl<T: PartialOrd, A: Allocator, G: GrowthPolicy> PartialOrd for Vec<T, A, G> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        PartialOrd::partial_cmp(&**self, &**other)
+    }
+}
+
+impl<T: Ord, A: Allocator, G: GrowthPolicy> Ord for Vec<T, A, G> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        Ord::cmp(&**self, &**other)
+    }
+}
+
+// same as the slice, so Borrow<[T]> is consistent for HashMap lookups
+impl<T: Hash, A: Allocator, G: GrowthPolicy> Hash for Vec<T, A, G> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Hash::hash(&**self, state)
+    }
+}
+
+impl<T, A: Allocator, G: GrowthPolicy> Extend<T> for Vec<T, A, G> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        handle_reserve(self.try_reserve(iter.size_hint().0));
+        for elem in iter {
+            self.push(elem);
+        }
+    }
+}
+
+impl<'a, T: Copy + 'a, A: Allocator, G: GrowthPolicy> Extend<&'a T> for Vec<T, A, G> {
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+impl<T> FromIterator<T> for Vec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = Self::new();
+        vec.extend(iter);
+        vec
+    }
+}
+
+impl<T, const N: usize> From<[T; N]> for Vec<T> {
+    fn from(arr: [T; N]) -> Self {
+        let mut vec = Self::with_capacity(N);
+        let arr = ManuallyDrop::new(arr);
+        unsafe {
+            ptr::copy_nonoverlapping(arr.as_ptr(), vec.as_ptr(), N);
+        }
+        vec.len = N;
+        vec
+    }
+}
+
+// Global allocates with std::alloc like std Vec does, so the buffer is reused
+impl<T> From<std::vec::Vec<T>> for Vec<T> {
+    fn from(vec: std::vec::Vec<T>) -> Self {
+        let mut vec = ManuallyDrop::new(vec);
+        let cap = if mem::size_of::<T>() == 0 {
+            0
+        } else {
+            vec.capacity()
+        };
+        Self {
+            data: RawVec {
+                ptr: Unique::new_unchecked(vec.as_mut_ptr()),
+                cap,
+                alloc: Global,
+                growth: Doubling,
+            },
+            len: vec.len(),
+        }
+    }
+}
+
+impl<T, A: Allocator, G: GrowthPolicy> AsRef<[T]> for Vec<T, A, G> {
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T, A: Allocator, G: GrowthPolicy> AsMut<[T]> for Vec<T, A, G> {
+    fn as_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T, A: Allocator, G: GrowthPolicy> Borrow<[T]> for Vec<T, A, G> {
+    fn borrow(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T, A: Allocator, G: GrowthPolicy> BorrowMut<[T]> for Vec<T, A, G> {
+    fn borrow_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::alloc::{BumpAlloc, CountingAlloc};
+    use super::growth::{Exact, GrowthStats, Instrumented, OneAndHalf, PageRounded};
+    use super::*;
+    use rand::distributions::{Distribution, Standard};
+    use rand::random;
+    use rkyv::ser::serializers::AllocSerializer;
+    use rkyv::ser::Serializer;
+    use rkyv::{Archive, Deserialize, Serialize};
+    use std::cell::Cell;
+
+    fn rand_op<T, A: Allocator>(my_vec: &mut Vec<T, A>, std_vec: &mut std::vec::Vec<T>)
+    where
+        T: Copy + PartialEq + fmt::Debug,
+        Standard: Distribution<T>,
+    {
+        let op = random::<i32>() % 5;
+        match op {
+            0 => {
+                // push
+                let val = random();
+                my_vec.push(val);
+                std_vec.push(val);
+                println!("push {:?}", val);
+            }
+            1 => {
+                // pop
+                assert_eq!(my_vec.pop(), std_vec.pop());
+                println!("pop");
+            }
+            2 => {
+                // insert
+                let idx = random::<usize>() % (my_vec.len() + 1);
+                let val = random::<T>();
+                my_vec.insert(idx, val);
+                std_vec.insert(idx, val);
+                println!("insert {} {:?}", idx, val);
+            }
+            3 => {
+                // get with slice
+                if !my_vec.is_empty() {
+                    let idx = random::<usize>() % my_vec.len();
+                    assert_eq!(my_vec[idx], std_vec[idx]);
+                    assert_eq!(my_vec.get(idx), std_vec.get(idx));
+                }
+                assert_eq!(my_vec.get(my_vec.len()), None);
+            }
+            4 => {
+                // put with slice
+                if !my_vec.is_empty() {
+                    let idx = random::<usize>() % my_vec.len();
+                    assert_eq!(my_vec[idx], std_vec[idx]);
+                    let val = random();
+                    my_vec[idx] = val;
+                    std_vec[idx] = val;
+                }
+            }
+            _ => {
+                // remove
+                if !my_vec.is_empty() {
+                    let idx = random::<usize>() % my_vec.len();
+                    my_vec.remove(idx);
+                    std_vec.remove(idx);
+                    println!("remove {}", idx);
+                }
+            }
+        }
+        assert_eq!(&my_vec[..], &std_vec[..]);
+    }
+
+    #[test]
+    fn test() {
+        let mut my_vec = Vec::new();
+        let mut std_vec = std::vec::Vec::<i32>::new();
+
+        for _ in 0..10000 {
+            rand_op(&mut my_vec, &mut std_vec);
+        }
+    }
+
+    #[test]
+    fn test_with_capacity() {
+        let mut my_vec = Vec::with_capacity(100);
+        let mut std_vec = std::vec::Vec::<i32>::new();
+
+        for _ in 0..10000 {
+            rand_op(&mut my_vec, &mut std_vec);
+        }
+    }
+
+    #[test]
+    fn test_bump_alloc() {
+        let bump = BumpAlloc::new();
+        let mut my_vec = Vec::new_in(&bump);
+        let mut std_vec = std::vec::Vec::<i32>::new();
+
+        for _ in 0..10000 {
+            rand_op(&mut my_vec, &mut std_vec);
+        }
+
+        // vectors in the same arena
+        let mut my_vec2 = Vec::with_capacity_in(10, &bump);
+        for i in 0..100 {
+            my_vec2.push(i);
+        }
+        let tail = my_vec2.split_off(50);
+        assert_eq!(&tail[..], &(50..100).collect::<std::vec::Vec<_>>()[..]);
+        assert_eq!(&my_vec[..], &std_vec[..]);
+    }
+
+    #[test]
+    fn test_counting_alloc() {
+        let counting = CountingAlloc::new();
+        {
+            let mut my_vec = Vec::new_in(&counting);
+            for i in 0..1000 {
+                my_vec.push(i as u64);
+            }
+            let stats = counting.stats();
+            assert_eq!(stats.allocs, 1);
+            assert_eq!(stats.reallocs, 10);
+            assert_eq!(stats.bytes, my_vec.capacity() * 8);
+
+            my_vec.shrink_to_fit();
+            assert_eq!(counting.stats().bytes, 1000 * 8);
+        }
+        let stats = counting.stats();
+        assert_eq!(stats.deallocs, 1);
+        assert_eq!(stats.bytes, 0);
+        assert_eq!(stats.peak_bytes, 1024 * 8);
+    }
+
+    #[test]
+    fn test_zst() {
+        let mut my_vec = Vec::<()>::new();
+        let mut std_vec = std::vec::Vec::new();
+        assert_eq!(my_vec.capacity(), usize::MAX);
+
+        for _ in 0..10000 {
+            rand_op(&mut my_vec, &mut std_vec);
+        }
+
+        let mut my_vec = Vec::<[u64; 0]>::with_capacity(100);
+        let mut std_vec = std::vec::Vec::new();
+        for _ in 0..10000 {
+            rand_op(&mut my_vec, &mut std_vec);
+        }
+
+        // no allocation at all
+        let counting = CountingAlloc::new();
+        let mut my_vec = Vec::with_capacity_in(10, &counting);
+        for _ in 0..1000 {
+            my_vec.push(());
+        }
+        my_vec.insert(500, ());
+        my_vec.remove(0);
+        my_vec.shrink_to_fit();
+        let tail = my_vec.split_off(100);
+        assert_eq!((my_vec.len(), tail.len()), (100, 900));
+        assert_eq!(my_vec.into_iter().count(), 100);
+        assert_eq!(tail.into_iter().rev().count(), 900);
+        assert_eq!(counting.stats(), Default::default());
+
+        let mut my_vec = Vec::<()>::new();
+        my_vec.len = usize::MAX;
+        assert_eq!(my_vec.try_push(()), Err(TryReserveError::CapacityOverflow));
+        my_vec.len = 0;
+    }
+
+    thread_local! {
+        static DROPS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    struct DropZst;
+
+    impl Drop for DropZst {
+        fn drop(&mut self) {
+            DROPS.with(|drops| drops.set(drops.get() + 1));
+        }
+    }
+
+    #[test]
+    fn test_zst_drop() {
+        let drops = || DROPS.with(|drops| drops.replace(0));
+        drops();
+
+        let mut my_vec = Vec::new();
+        for _ in 0..100 {
+            my_vec.push(DropZst);
+        }
+        my_vec.pop();
+        my_vec.remove(10);
+        assert_eq!(drops(), 2);
+        my_vec.truncate(50);
+        assert_eq!(drops(), 48);
+
+        let mut into_iter = my_vec.into_iter();
+        into_iter.next();
+        into_iter.next_back();
+        assert_eq!(drops(), 2);
+        drop(into_iter);
+        assert_eq!(drops(), 48);
+
+        let mut my_vec = Vec::new();
+        for _ in 0..10 {
+            my_vec.push(DropZst);
+        }
+        my_vec.drain(2..5);
+        assert_eq!(drops(), 3);
+        drop(my_vec);
+        assert_eq!(drops(), 7);
+    }
+
+    struct NoAlloc;
+
+    unsafe impl Allocator for NoAlloc {
+        unsafe fn alloc(&self, _layout: Layout) -> *mut u8 {
+            ptr::null_mut()
+        }
+
+        unsafe fn dealloc(&self, _ptr: *mut u8, _layout: Layout) {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn test_try_reserve() {
+        let mut my_vec = Vec::<u64>::new();
+        assert_eq!(
+            my_vec.try_reserve(usize::MAX),
+            Err(TryReserveError::CapacityOverflow)
+        );
+        assert_eq!(
+            my_vec.try_reserve_exact(isize::MAX as usize / 8 + 1),
+            Err(TryReserveError::CapacityOverflow)
+        );
+        assert!(Vec::<u64>::try_with_capacity(usize::MAX / 4).is_err());
+
+        my_vec.try_reserve_exact(10).unwrap();
+        assert_eq!(my_vec.capacity(), 10);
+        for i in 0..10 {
+            my_vec.try_push(i).unwrap();
+        }
+        assert_eq!(my_vec.capacity(), 10);
+        my_vec.try_reserve(1).unwrap();
+        assert_eq!(my_vec.capacity(), 20);
+        assert_eq!(&my_vec[..], &(0..10).collect::<std::vec::Vec<_>>()[..]);
+
+        let layout = Layout::array::<u64>(4).unwrap();
+        let mut no_alloc = Vec::<u64, _>::new_in(NoAlloc);
+        assert_eq!(
+            no_alloc.try_reserve(4),
+            Err(TryReserveError::AllocError { layout })
+        );
+        assert!(no_alloc.try_push(1).is_err());
+        assert_eq!(no_alloc.len(), 0);
+        assert!(Vec::<u64, _>::try_with_capacity_in(4, NoAlloc).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity overflow")]
+    fn test_reserve_overflow() {
+        let mut my_vec = Vec::<u64>::new();
+        my_vec.reserve(usize::MAX);
+    }
+
+    #[test]
+    fn test_shrink() {
+        let mut my_vec = Vec::new();
+        let mut std_vec = std::vec::Vec::new();
+
+        for _ in 0..100 {
+            for _ in 0..100 {
+                rand_op(&mut my_vec, &mut std_vec);
+            }
+
+            let len = my_vec.len();
+            my_vec.shrink_to(len + 10);
+            assert!(my_vec.capacity() <= len + 10);
+            my_vec.shrink_to_fit();
+            assert_eq!(my_vec.capacity(), len);
+            assert_eq!(&my_vec[..], &std_vec[..]);
+        }
+
+        my_vec.clear();
+        my_vec.shrink_to_fit();
+        assert_eq!(my_vec.capacity(), 0);
+        my_vec.push(1);
+        assert_eq!(&my_vec[..], &[1]);
+    }
+
+    #[test]
+    fn test_truncate_split_off() {
+        let mut my_vec = Vec::new();
+        let mut std_vec = std::vec::Vec::new();
+        for _ in 0..1000 {
+            let val = random::<i32>();
+            my_vec.push(val);
+            std_vec.push(val);
+        }
+
+        let (my_tail, std_tail) = (my_vec.split_off(300), std_vec.split_off(300));
+        assert_eq!(&my_vec[..], &std_vec[..]);
+        assert_eq!(&my_tail[..], &std_tail[..]);
+
+        my_vec.truncate(1000);
+        assert_eq!(my_vec.len(), 300);
+        my_vec.truncate(10);
+        std_vec.truncate(10);
+        assert_eq!(&my_vec[..], &std_vec[..]);
+
+        // truncate must drop the removed elements
+        let rc = std::rc::Rc::new(());
+        let mut rc_vec = Vec::new();
+        for _ in 0..10 {
+            rc_vec.push(rc.clone());
+        }
+        rc_vec.truncate(4);
+        assert_eq!(std::rc::Rc::strong_count(&rc), 5);
+        let rc_tail = rc_vec.split_off(1);
+        assert_eq!(std::rc::Rc::strong_count(&rc), 5);
+        drop(rc_tail);
+        rc_vec.clear();
+        assert_eq!(std::rc::Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn test_traits() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::collections::HashSet;
+
+        let mut my_vec: Vec<i32> = (0..10).collect();
+        my_vec.extend([10, 11]);
+        my_vec.extend(&[12, 13]);
+        assert_eq!(my_vec, (0..14).collect::<std::vec::Vec<_>>()[..]);
+        assert_eq!(
+            my_vec,
+            vector![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13]
+        );
+        assert_eq!(
+            format!("{:?}", my_vec),
+            format!("{:?}", (0..14).collect::<std::vec::Vec<_>>())
+        );
+
+        for elem in &mut my_vec {
+            *elem *= 2;
+        }
+        assert_eq!((&my_vec).into_iter().sum::<i32>(), 182);
+
+        let clone = my_vec.clone();
+        assert_eq!(clone, my_vec);
+        assert!(clone <= my_vec);
+        my_vec.push(0);
+        assert!(clone < my_vec);
+        assert_eq!(clone.cmp(&my_vec), std::cmp::Ordering::Less);
+
+        let hash = |val: &dyn Fn(&mut DefaultHasher)| {
+            let mut hasher = DefaultHasher::new();
+            val(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash(&|h| clone.hash(h)), hash(&|h| clone.as_ref().hash(h)));
+        let set: HashSet<Vec<i32>> = std::iter::once(clone).collect();
+        assert!(set.contains(&[0, 2, 4, 6, 8, 10, 12, 14, 16, 18, 20, 22, 24, 26][..]));
+
+        let from_std = Vec::from(vec![String::from("a"), String::from("b")]);
+        assert_eq!(from_std, ["a", "b"]);
+        assert_eq!(vector![String::from("x"); 3], ["x", "x", "x"]);
+        assert_eq!(vector![String::new(); 0].len(), 0);
+        let empty: Vec<()> = vector![];
+        assert_eq!(empty.len(), 0);
+        assert_eq!(Vec::from(vec![(); 5]).len(), 5);
+    }
+
+    #[test]
+    fn test_clone_panic() {
+        thread_local! {
+            static CLONES: Cell<usize> = const { Cell::new(0) };
+        }
+
+        struct Bomb(std::rc::Rc<()>);
+        impl Clone for Bomb {
+            fn clone(&self) -> Self {
+                CLONES.with(|c| c.set(c.get() + 1));
+                if CLONES.with(|c| c.get()) == 5 {
+                    panic!("boom");
+                }
+                Bomb(self.0.clone())
+            }
+        }
+
+        let rc = std::rc::Rc::new(());
+        let my_vec: Vec<Bomb> = (0..10).map(|_| Bomb(rc.clone())).collect();
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| my_vec.clone()));
+        assert!(res.is_err());
+        // the 4 clones made before the panic are dropped
+        assert_eq!(std::rc::Rc::strong_count(&rc), 11);
+    }
+
+    #[test]
+    fn test_growth_policy() {
+        fn run<G: GrowthPolicy>(growth: G) -> (Vec<u64, Global, Instrumented<G>>, GrowthStats) {
+            let mut my_vec = Vec::with_growth(Instrumented::new(growth));
+            for i in 0..20_000 {
+                my_vec.push(i);
+            }
+            assert!(my_vec.iter().copied().eq(0..20_000));
+            let stats = my_vec.growth().stats();
+            assert_eq!(stats.peak_cap, my_vec.capacity());
+            assert!(stats.reallocs > 0);
+            assert!(stats.bytes_copied >= stats.reallocs * 8);
+            (my_vec, stats)
+        }
+
+        let (_, doubling) = run(Doubling);
+        let (_, one_and_half) = run(OneAndHalf);
+        let (_, page) = run(PageRounded::new());
+        let (mut exact_vec, exact) = run(Exact);
+
+        assert_eq!(doubling.reallocs, 15);
+        assert_eq!(doubling.peak_cap, 32768);
+        assert!(one_and_half.reallocs > doubling.reallocs);
+        assert!(one_and_half.peak_cap <= 30_000);
+        assert!(page.reallocs <= doubling.reallocs);
+        assert_eq!(page.peak_cap % 512, 0);
+        assert_eq!(exact.reallocs, 19_999);
+        assert_eq!(exact.peak_cap, 20_000);
+        assert_eq!(exact.bytes_copied, (1..20_000).sum::<usize>() * 8);
+
+        // the try_reserve apis follow the policy too
+        exact_vec.growth().reset();
+        exact_vec.try_reserve(10).unwrap();
+        assert_eq!(exact_vec.capacity(), 20_010);
+        exact_vec.shrink_to_fit();
+        assert_eq!(exact_vec.growth().stats().reallocs, 2);
+        let clone = exact_vec.clone();
+        assert_eq!(clone, exact_vec);
+        assert_eq!(clone.growth().stats().reallocs, 0);
+    }
+
+    #[test]
+    fn test_aligned() {
+        fn check<T: Copy + PartialEq + fmt::Debug, const ALIGN: usize>(val: T)
+        where
+            Standard: Distribution<T>,
+        {
+            let mut my_vec: AlignedVec<T, ALIGN> = Vec::new_in(Aligned::new());
+            let mut std_vec = std::vec::Vec::new();
+            my_vec.push(val);
+            std_vec.push(val);
+            for _ in 0..1000 {
+                rand_op(&mut my_vec, &mut std_vec);
+                assert_eq!(my_vec.as_ptr() as usize % ALIGN, 0);
+            }
+            my_vec.shrink_to_fit();
+            if my_vec.capacity() > 0 {
+                assert_eq!(my_vec.as_ptr() as usize % ALIGN, 0);
+            }
+        }
+
+        check::<u8, 16>(0);
+        check::<f32, 32>(0.0);
+        check::<u64, 64>(0);
+        check::<u8, 4096>(0);
+
+        // over-aligned in an arena too
+        let bump = BumpAlloc::new();
+        let mut my_vec = Vec::new_in(Aligned::<_, 256>::new_in(&bump));
+        for i in 0..100u8 {
+            my_vec.push(i);
+            assert_eq!(my_vec.as_ptr() as usize % 256, 0);
+        }
+    }
+
+    #[test]
+    fn test_aligned_zero_copy() {
+        // view the bytes as u64 without copying
+        let mut bytes: AlignedVec<u8, 8> = Vec::new_in(Aligned::new());
+        for i in 0..4u64 {
+            bytes.extend_from_slice(&i.to_ne_bytes());
+        }
+        let words = zerocopy::LayoutVerified::<_, [u64]>::new_slice(&bytes[..]).unwrap();
+        assert_eq!(&*words, &[0, 1, 2, 3]);
+
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Point {
+            x: u64,
+            y: u32,
+            name: String,
+        }
+
+        let point = Point {
+            x: 1,
+            y: 2,
+            name: String::from("origin"),
+        };
+        let mut serializer = AllocSerializer::<256>::default();
+        serializer.serialize_value(&point).unwrap();
+        let archive = serializer.into_serializer().into_inner();
+        let mut buf: AlignedVec<u8, 16> = Vec::new_in(Aligned::new());
+        buf.extend_from_slice(&archive);
+        let archived = unsafe { rkyv::archived_root::<Point>(&buf) };
+        assert_eq!(archived.x, 1);
+        assert_eq!(archived.name, "origin");
+        let point2: Point = archived.deserialize(&mut rkyv::Infallible).unwrap();
+        assert_eq!(point, point2);
+    }
+}
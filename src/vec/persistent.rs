@@ -0,0 +1,792 @@
+use super::Vec;
+use crate::ptr_kind::{ArcK, PointerKind, RcK};
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::Index;
+use std::slice;
+
+const BITS: usize = 5;
+const WIDTH: usize = 1 << BITS;
+// a concat may leave this many nodes more than the optimum on each level
+const EXTRAS: usize = 2;
+
+type Ptr<T, P> = <P as PointerKind>::Ptr<Node<T, P>>;
+type Halves<T, P> = (Option<Ptr<T, P>>, Option<Ptr<T, P>>);
+
+enum Node<T, P: PointerKind> {
+    Leaf(Vec<T>),
+    Branch {
+        children: Vec<Ptr<T, P>>,
+        // cumulative lengths of the children, None if every child but the
+        // last is full so the child can be found with a shift
+        sizes: Option<Vec<usize>>,
+    },
+}
+
+impl<T: Clone, P: PointerKind> Clone for Node<T, P> {
+    fn clone(&self) -> Self {
+        match self {
+            Node::Leaf(elems) => Node::Leaf(elems.clone()),
+            Node::Branch { children, sizes } => Node::Branch {
+                children: children.clone(),
+                sizes: sizes.clone(),
+            },
+        }
+    }
+}
+
+// `shift` of a node is BITS * its height, a child of a branch holds at most
+// 1 << shift elements
+impl<T, P: PointerKind> Node<T, P> {
+    // build a branch, the size table is only kept if it is needed
+    fn branch(children: Vec<Ptr<T, P>>, shift: usize) -> Self {
+        let n = children.len();
+        let dense = children.iter().enumerate().all(|(i, child)| {
+            child.is_dense() && (i + 1 == n || child.len(shift - BITS) == 1 << shift)
+        });
+        let sizes = if dense {
+            None
+        } else {
+            let mut total = 0;
+            Some(
+                children
+                    .iter()
+                    .map(|child| {
+                        total += child.len(shift - BITS);
+                        total
+                    })
+                    .collect(),
+            )
+        };
+        Node::Branch { children, sizes }
+    }
+
+    fn len(&self, shift: usize) -> usize {
+        match self {
+            Node::Leaf(elems) => elems.len(),
+            Node::Branch {
+                sizes: Some(sizes), ..
+            } => *sizes.last().unwrap(),
+            Node::Branch {
+                children,
+                sizes: None,
+            } => ((children.len() - 1) << shift) + children.last().unwrap().len(shift - BITS),
+        }
+    }
+
+    fn is_dense(&self) -> bool {
+        !matches!(self, Node::Branch { sizes: Some(_), .. })
+    }
+
+    // elements of a leaf or children of a branch
+    fn slots(&self) -> usize {
+        match self {
+            Node::Leaf(elems) => elems.len(),
+            Node::Branch { children, .. } => children.len(),
+        }
+    }
+
+    fn children(&self) -> &[Ptr<T, P>] {
+        match self {
+            Node::Leaf(_) => unreachable!("children of a leaf"),
+            Node::Branch { children, .. } => children,
+        }
+    }
+
+    // the child holding idx, and idx relative to that child
+    fn position(&self, idx: usize, shift: usize) -> (usize, usize) {
+        match self {
+            Node::Leaf(_) => unreachable!("position in a leaf"),
+            Node::Branch { sizes: None, .. } => (idx >> shift, idx & ((1 << shift) - 1)),
+            Node::Branch {
+                sizes: Some(sizes), ..
+            } => {
+                // no child holds more than 1 << shift, so this is a lower bound
+                let mut i = idx >> shift;
+                while sizes[i] <= idx {
+                    i += 1;
+                }
+                (i, if i == 0 { idx } else { idx - sizes[i - 1] })
+            }
+        }
+    }
+}
+
+impl<T: Clone, P: PointerKind> Node<T, P> {
+    // push into the subtree, return a new sibling when it is full
+    fn push(node: &mut Ptr<T, P>, val: T) -> Option<Ptr<T, P>> {
+        match P::make_mut(node) {
+            Node::Leaf(elems) if elems.len() < WIDTH => {
+                elems.push(val);
+                None
+            }
+            Node::Leaf(_) => Some(P::new(Node::Leaf(Vec::from([val])))),
+            Node::Branch { children, sizes } => {
+                let last = children.last_mut().unwrap();
+                match Self::push(last, val) {
+                    None => {
+                        if let Some(sizes) = sizes {
+                            *sizes.last_mut().unwrap() += 1;
+                        }
+                        None
+                    }
+                    Some(child) if children.len() < WIDTH => {
+                        // a dense child only overflows when it is full, so a
+                        // dense branch stays dense
+                        children.push(child);
+                        if let Some(sizes) = sizes {
+                            let total = *sizes.last().unwrap();
+                            sizes.push(total + 1);
+                        }
+                        None
+                    }
+                    Some(child) => Some(P::new(Node::Branch {
+                        children: Vec::from([child]),
+                        sizes: None,
+                    })),
+                }
+            }
+        }
+    }
+
+    // pop from the subtree, also return whether it is empty now
+    fn pop(node: &mut Ptr<T, P>) -> (T, bool) {
+        match P::make_mut(node) {
+            Node::Leaf(elems) => {
+                let val = elems.pop().unwrap();
+                (val, elems.is_empty())
+            }
+            Node::Branch { children, sizes } => {
+                let (val, empty) = Self::pop(children.last_mut().unwrap());
+                if empty {
+                    children.pop();
+                    if let Some(sizes) = sizes {
+                        sizes.pop();
+                    }
+                } else if let Some(sizes) = sizes {
+                    *sizes.last_mut().unwrap() -= 1;
+                }
+                (val, children.is_empty())
+            }
+        }
+    }
+
+    fn set(node: &mut Ptr<T, P>, shift: usize, idx: usize, val: T) {
+        let (i, idx) = match &**node {
+            Node::Leaf(_) => (idx, 0),
+            branch => branch.position(idx, shift),
+        };
+        match P::make_mut(node) {
+            Node::Leaf(elems) => elems[i] = val,
+            Node::Branch { children, .. } => Self::set(&mut children[i], shift - BITS, idx, val),
+        }
+    }
+
+    // Concatenate two subtrees along the seam, return the new nodes on the
+    // level of the taller one, one or two of them.
+    fn concat(left: &Ptr<T, P>, lshift: usize, right: &Ptr<T, P>, rshift: usize) -> Vec<Ptr<T, P>> {
+        let (mut nodes, shift) = if lshift > rshift {
+            let children = left.children();
+            let mid = Self::concat(children.last().unwrap(), lshift - BITS, right, rshift);
+            (join(&children[..children.len() - 1], &mid, &[]), lshift)
+        } else if lshift < rshift {
+            let children = right.children();
+            let mid = Self::concat(left, lshift, &children[0], rshift - BITS);
+            (join(&[], &mid, &children[1..]), rshift)
+        } else if lshift == 0 {
+            // leaves are merged if they fit, the parent rebalances the rest
+            if left.slots() + right.slots() > WIDTH {
+                return Vec::from([left.clone(), right.clone()]);
+            }
+            let mut elems = Vec::new();
+            for leaf in [left, right] {
+                if let Node::Leaf(leaf) = &**leaf {
+                    elems.extend_from_slice(leaf);
+                }
+            }
+            return Vec::from([P::new(Node::Leaf(elems))]);
+        } else {
+            let (lchildren, rchildren) = (left.children(), right.children());
+            let mid = Self::concat(
+                lchildren.last().unwrap(),
+                lshift - BITS,
+                &rchildren[0],
+                rshift - BITS,
+            );
+            (
+                join(&lchildren[..lchildren.len() - 1], &mid, &rchildren[1..]),
+                lshift,
+            )
+        };
+
+        rebalance::<T, P>(&mut nodes, shift - BITS);
+        let mut parents = Vec::new();
+        while !nodes.is_empty() {
+            let rest = nodes.split_off(std::cmp::min(WIDTH, nodes.len()));
+            parents.push(P::new(Node::branch(nodes, shift)));
+            nodes = rest;
+        }
+        parents
+    }
+
+    // split the subtree before idx
+    fn split(node: &Ptr<T, P>, shift: usize, idx: usize) -> Halves<T, P> {
+        if idx == 0 {
+            return (None, Some(node.clone()));
+        }
+        if idx == node.len(shift) {
+            return (Some(node.clone()), None);
+        }
+
+        match &**node {
+            Node::Leaf(elems) => {
+                let (mut left, mut right) = (Vec::new(), Vec::new());
+                left.extend_from_slice(&elems[..idx]);
+                right.extend_from_slice(&elems[idx..]);
+                (
+                    Some(P::new(Node::Leaf(left))),
+                    Some(P::new(Node::Leaf(right))),
+                )
+            }
+            Node::Branch { children, .. } => {
+                let (i, idx) = node.position(idx, shift);
+                let (l, r) = Self::split(&children[i], shift - BITS, idx);
+                let left = join(&children[..i], l.as_slice(), &[]);
+                let right = join(&[], r.as_slice(), &children[i + 1..]);
+                let wrap = |children: Vec<Ptr<T, P>>| {
+                    if children.is_empty() {
+                        None
+                    } else {
+                        Some(P::new(Node::branch(children, shift)))
+                    }
+                };
+                (wrap(left), wrap(right))
+            }
+        }
+    }
+}
+
+fn join<T: Clone>(a: &[T], b: &[T], c: &[T]) -> Vec<T> {
+    let mut vec = Vec::new();
+    vec.extend_from_slice(a);
+    vec.extend_from_slice(b);
+    vec.extend_from_slice(c);
+    vec
+}
+
+// The concatenation plan of RRB trees: while there are more than EXTRAS
+// nodes above the optimum, spread the slots of the first short node over the
+// nodes after it. Nodes that are not touched are reused.
+fn rebalance<T: Clone, P: PointerKind>(nodes: &mut Vec<Ptr<T, P>>, shift: usize) {
+    let mut counts: std::vec::Vec<usize> = nodes.iter().map(|node| node.slots()).collect();
+    let total: usize = counts.iter().sum();
+    let optimal = total.div_ceil(WIDTH);
+    if counts.len() <= optimal + EXTRAS {
+        return;
+    }
+
+    let mut i = 0;
+    while counts.len() > optimal + EXTRAS {
+        while counts[i] >= WIDTH - EXTRAS / 2 {
+            i += 1;
+        }
+        let mut remaining = counts[i];
+        while remaining > 0 {
+            let size = std::cmp::min(remaining + counts[i + 1], WIDTH);
+            counts[i] = size;
+            remaining = remaining + counts[i + 1] - size;
+            i += 1;
+        }
+        counts.remove(i);
+        i -= 1;
+    }
+
+    // refill the nodes with the planned counts, reading slots in order
+    let old = std::mem::take(nodes);
+    let (mut src, mut offset) = (0, 0);
+    for count in counts {
+        if offset == 0 && old[src].slots() == count {
+            nodes.push(old[src].clone());
+            src += 1;
+            continue;
+        }
+
+        let node = match &*old[src] {
+            Node::Leaf(_) => {
+                let mut elems = Vec::new();
+                while elems.len() < count {
+                    if let Node::Leaf(from) = &*old[src] {
+                        let n = std::cmp::min(count - elems.len(), from.len() - offset);
+                        elems.extend_from_slice(&from[offset..offset + n]);
+                        offset += n;
+                        if offset == from.len() {
+                            src += 1;
+                            offset = 0;
+                        }
+                    }
+                }
+                Node::Leaf(elems)
+            }
+            Node::Branch { .. } => {
+                let mut children = Vec::new();
+                while children.len() < count {
+                    let from = old[src].children();
+                    let n = std::cmp::min(count - children.len(), from.len() - offset);
+                    children.extend_from_slice(&from[offset..offset + n]);
+                    offset += n;
+                    if offset == from.len() {
+                        src += 1;
+                        offset = 0;
+                    }
+                }
+                Node::branch(children, shift)
+            }
+        };
+        nodes.push(P::new(node));
+    }
+}
+
+// Persistent vector (RRB tree), every update returns a new version and shares
+// the untouched nodes with the old one. get/set/push/pop are O(log32 n),
+// concat and split_at are O(log n) too.
+pub struct PersistentVec<T, P: PointerKind = RcK> {
+    root: Option<Ptr<T, P>>,
+    len: usize,
+    shift: usize,
+}
+
+pub type SyncPersistentVec<T> = PersistentVec<T, ArcK>;
+
+impl<T, P: PointerKind> PersistentVec<T, P> {
+    pub fn new() -> Self {
+        Self {
+            root: None,
+            len: 0,
+            shift: 0,
+        }
+    }
+
+    // collapse a root with one child
+    fn from_root(root: Option<Ptr<T, P>>, mut shift: usize, len: usize) -> Self {
+        let mut root = match root {
+            Some(root) if len > 0 => root,
+            _ => return Self::new(),
+        };
+        while let Node::Branch { children, .. } = &*root {
+            if children.len() != 1 {
+                break;
+            }
+            let child = children[0].clone();
+            root = child;
+            shift -= BITS;
+        }
+        Self {
+            root: Some(root),
+            len,
+            shift,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&T> {
+        if idx >= self.len {
+            return None;
+        }
+
+        let (mut node, mut idx, mut shift) = (&**self.root.as_ref()?, idx, self.shift);
+        loop {
+            match node {
+                Node::Leaf(elems) => return elems.get(idx),
+                Node::Branch { children, .. } => {
+                    let (i, child_idx) = node.position(idx, shift);
+                    node = &children[i];
+                    idx = child_idx;
+                    shift -= BITS;
+                }
+            }
+        }
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.get(self.len.wrapping_sub(1))
+    }
+
+    pub fn iter(&self) -> Iter<'_, T, P> {
+        let mut iter = Iter {
+            stack: Vec::new(),
+            leaf: [].iter(),
+            len: self.len,
+        };
+        if let Some(root) = &self.root {
+            iter.descend(root);
+        }
+        iter
+    }
+}
+
+impl<T: Clone, P: PointerKind> PersistentVec<T, P> {
+    fn push_back_mut(&mut self, val: T) {
+        match &mut self.root {
+            None => self.root = Some(P::new(Node::Leaf(Vec::from([val])))),
+            Some(root) => {
+                if let Some(sibling) = Node::<T, P>::push(root, val) {
+                    let children = Vec::from([root.clone(), sibling]);
+                    self.shift += BITS;
+                    self.root = Some(P::new(Node::branch(children, self.shift)));
+                }
+            }
+        }
+        self.len += 1;
+    }
+
+    pub fn push_back(&self, val: T) -> Self {
+        let mut vec = self.clone();
+        vec.push_back_mut(val);
+        vec
+    }
+
+    // the vector without its last element, or an empty one
+    pub fn pop_back(&self) -> Self {
+        let mut vec = self.clone();
+        if let Some(root) = &mut vec.root {
+            Node::<T, P>::pop(root);
+            vec = Self::from_root(vec.root, vec.shift, vec.len - 1);
+        }
+        vec
+    }
+
+    pub fn set(&self, idx: usize, val: T) -> Self {
+        assert!(idx < self.len, "index out of bounds");
+        let mut vec = self.clone();
+        Node::<T, P>::set(vec.root.as_mut().unwrap(), vec.shift, idx, val);
+        vec
+    }
+
+    pub fn concat(&self, other: &Self) -> Self {
+        let (left, right) = match (&self.root, &other.root) {
+            (_, None) => return self.clone(),
+            (None, _) => return other.clone(),
+            (Some(left), Some(right)) => (left, right),
+        };
+
+        let shift = std::cmp::max(self.shift, other.shift);
+        let mut nodes = Node::<T, P>::concat(left, self.shift, right, other.shift);
+        let len = self.len + other.len;
+        if nodes.len() == 1 {
+            Self::from_root(nodes.pop(), shift, len)
+        } else {
+            let root = P::new(Node::branch(nodes, shift + BITS));
+            Self::from_root(Some(root), shift + BITS, len)
+        }
+    }
+
+    // ([0, idx), [idx, len))
+    pub fn split_at(&self, idx: usize) -> (Self, Self) {
+        assert!(idx <= self.len, "split index out of bounds");
+        match &self.root {
+            None => (Self::new(), Self::new()),
+            Some(root) => {
+                let (left, right) = Node::<T, P>::split(root, self.shift, idx);
+                (
+                    Self::from_root(left, self.shift, idx),
+                    Self::from_root(right, self.shift, self.len - idx),
+                )
+            }
+        }
+    }
+}
+
+impl<T, P: PointerKind> Clone for PersistentVec<T, P> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            len: self.len,
+            shift: self.shift,
+        }
+    }
+}
+
+impl<T, P: PointerKind> Default for PersistentVec<T, P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, P: PointerKind> Index<usize> for PersistentVec<T, P> {
+    type Output = T;
+    fn index(&self, idx: usize) -> &T {
+        self.get(idx).expect("index out of bounds")
+    }
+}
+
+impl<T: Clone, P: PointerKind> FromIterator<T> for PersistentVec<T, P> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = Self::new();
+        for val in iter {
+            vec.push_back_mut(val);
+        }
+        vec
+    }
+}
+
+impl<T: PartialEq, P: PointerKind> PartialEq for PersistentVec<T, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq, P: PointerKind> Eq for PersistentVec<T, P> {}
+
+impl<T: fmt::Debug, P: PointerKind> fmt::Debug for PersistentVec<T, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T, P: PointerKind> IntoIterator for &'a PersistentVec<T, P> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, P>;
+
+    fn into_iter(self) -> Iter<'a, T, P> {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a, T, P: PointerKind + 'a> {
+    // branches above the current leaf, with the next child to visit
+    stack: Vec<(&'a [Ptr<T, P>], usize)>,
+    leaf: slice::Iter<'a, T>,
+    len: usize,
+}
+
+impl<'a, T, P: PointerKind + 'a> Iter<'a, T, P> {
+    fn descend(&mut self, mut node: &'a Node<T, P>) {
+        loop {
+            match node {
+                Node::Leaf(elems) => {
+                    self.leaf = elems.iter();
+                    return;
+                }
+                Node::Branch { children, .. } => {
+                    self.stack.push((children, 1));
+                    node = &children[0];
+                }
+            }
+        }
+    }
+}
+
+impl<'a, T, P: PointerKind + 'a> Iterator for Iter<'a, T, P> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        loop {
+            if let Some(val) = self.leaf.next() {
+                self.len -= 1;
+                return Some(val);
+            }
+            let (children, next) = self.stack.last_mut()?;
+            let children: &'a [Ptr<T, P>] = children;
+            if *next < children.len() {
+                let child = &children[*next];
+                *next += 1;
+                self.descend(child);
+            } else {
+                self.stack.pop();
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T, P: PointerKind> ExactSizeIterator for Iter<'_, T, P> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::random;
+
+    // check the length of every node, and that dense nodes are really dense
+    fn check<T, P: PointerKind>(vec: &PersistentVec<T, P>) {
+        fn check_node<T, P: PointerKind>(node: &Node<T, P>, shift: usize) -> usize {
+            match node {
+                Node::Leaf(elems) => {
+                    assert!(!elems.is_empty() && elems.len() <= WIDTH);
+                    elems.len()
+                }
+                Node::Branch { children, sizes } => {
+                    assert!(!children.is_empty() && children.len() <= WIDTH);
+                    let lens: std::vec::Vec<_> = children
+                        .iter()
+                        .map(|child| check_node(child, shift - BITS))
+                        .collect();
+                    match sizes {
+                        None => {
+                            let n = lens.len();
+                            assert!(lens[..n - 1].iter().all(|len| *len == 1 << shift));
+                        }
+                        Some(sizes) => {
+                            let mut total = 0;
+                            for (len, size) in lens.iter().zip(sizes.iter()) {
+                                total += len;
+                                assert_eq!(total, *size);
+                            }
+                        }
+                    }
+                    lens.iter().sum()
+                }
+            }
+        }
+
+        match &vec.root {
+            None => assert_eq!(vec.len, 0),
+            Some(root) => assert_eq!(check_node(root, vec.shift), vec.len),
+        }
+    }
+
+    fn gen(len: usize) -> (PersistentVec<i32>, std::vec::Vec<i32>) {
+        let std_vec: std::vec::Vec<i32> = (0..len).map(|_| random()).collect();
+        (std_vec.iter().copied().collect(), std_vec)
+    }
+
+    fn assert_same(my_vec: &PersistentVec<i32>, std_vec: &[i32]) {
+        check(my_vec);
+        assert_eq!(my_vec.len(), std_vec.len());
+        assert!(my_vec.iter().eq(std_vec.iter()));
+        for (i, val) in std_vec.iter().enumerate() {
+            assert_eq!(my_vec.get(i), Some(val));
+        }
+        assert_eq!(my_vec.get(std_vec.len()), None);
+    }
+
+    #[test]
+    fn test() {
+        let mut versions = std::vec::Vec::new();
+        let (mut my_vec, mut std_vec) = gen(0);
+        for _ in 0..5000 {
+            match random::<u32>() % 4 {
+                0 => {
+                    my_vec = my_vec.pop_back();
+                    std_vec.pop();
+                }
+                1 if !std_vec.is_empty() => {
+                    let idx = random::<usize>() % std_vec.len();
+                    let val = random();
+                    my_vec = my_vec.set(idx, val);
+                    std_vec[idx] = val;
+                }
+                _ => {
+                    let val = random();
+                    my_vec = my_vec.push_back(val);
+                    std_vec.push(val);
+                }
+            }
+            assert_eq!(my_vec.last(), std_vec.last());
+            if random::<u32>() % 100 == 0 {
+                versions.push((my_vec.clone(), std_vec.clone()));
+            }
+        }
+        assert_same(&my_vec, &std_vec);
+
+        // old versions are not changed by the later updates
+        for (my_vec, std_vec) in &versions {
+            assert_same(my_vec, std_vec);
+        }
+    }
+
+    #[test]
+    fn concat_split() {
+        for _ in 0..200 {
+            let (a, a_std) = gen(random::<usize>() % 2000);
+            let (b, b_std) = gen(random::<usize>() % 2000);
+            let ab = a.concat(&b);
+            let ab_std = [&a_std[..], &b_std[..]].concat();
+            assert_same(&ab, &ab_std);
+            assert_same(&a, &a_std);
+
+            let idx = random::<usize>() % (ab_std.len() + 1);
+            let (left, right) = ab.split_at(idx);
+            assert_same(&left, &ab_std[..idx]);
+            assert_same(&right, &ab_std[idx..]);
+
+            // relaxed trees still support updates
+            let right = right.push_back(1).set(0, 2).pop_back();
+            let mut right_std = ab_std[idx..].to_vec();
+            right_std.push(1);
+            right_std[0] = 2;
+            right_std.pop();
+            assert_same(&right, &right_std);
+        }
+    }
+
+    #[test]
+    fn concat_many() {
+        // many small pieces must not make the tree deep
+        let mut my_vec = PersistentVec::<i32>::new();
+        let mut std_vec = std::vec::Vec::new();
+        for _ in 0..2000 {
+            let (piece, piece_std) = gen(random::<usize>() % 40);
+            if random() {
+                my_vec = my_vec.concat(&piece);
+                std_vec.extend_from_slice(&piece_std);
+            } else {
+                my_vec = piece.concat(&my_vec);
+                std_vec.splice(0..0, piece_std);
+            }
+        }
+        assert_same(&my_vec, &std_vec);
+        assert!(
+            my_vec.shift <= 4 * BITS,
+            "too deep: {}",
+            my_vec.shift / BITS
+        );
+
+        // and chop it up again
+        let mut pieces = std::vec::Vec::new();
+        let mut rest = my_vec;
+        while !rest.is_empty() {
+            let (piece, tail) = rest.split_at(std::cmp::min(rest.len(), random::<usize>() % 500));
+            pieces.push(piece);
+            rest = tail;
+        }
+        let joined = pieces
+            .iter()
+            .fold(PersistentVec::new(), |acc, piece| acc.concat(piece));
+        assert_same(&joined, &std_vec);
+    }
+
+    #[test]
+    fn sync() {
+        let vec: SyncPersistentVec<usize> = (0..10000).collect();
+        let handles: std::vec::Vec<_> = (0..4)
+            .map(|t| {
+                let vec = vec.clone();
+                std::thread::spawn(move || {
+                    let vec = vec.set(t, 0).push_back(t);
+                    assert_eq!(vec[t], 0);
+                    vec
+                })
+            })
+            .collect();
+        for (t, handle) in handles.into_iter().enumerate() {
+            let new = handle.join().unwrap();
+            assert_eq!(new.len(), 10001);
+            assert_eq!(new.last(), Some(&t));
+        }
+        assert!(vec.iter().copied().eq(0..10000));
+    }
+}
@@ -0,0 +1,284 @@
+use super::alloc::Global;
+use super::{handle_reserve, RawVec, TryReserveError};
+use std::mem::{self, MaybeUninit};
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+use std::slice;
+
+/// The inline buffer of SmallVec, implemented for [T; N].
+///
+/// # Safety
+///
+/// The type must be laid out as CAP consecutive Items.
+pub unsafe trait Array {
+    type Item;
+    const CAP: usize;
+}
+
+unsafe impl<T, const N: usize> Array for [T; N] {
+    type Item = T;
+    const CAP: usize = N;
+}
+
+// keep up to A::CAP elements inline, move to the heap when it grows past that
+pub struct SmallVec<A: Array> {
+    len: usize,
+    data: Data<A>,
+}
+
+enum Data<A: Array> {
+    Inline(MaybeUninit<A>),
+    Heap(RawVec<A::Item>),
+}
+
+impl<A: Array> SmallVec<A> {
+    pub fn new() -> Self {
+        Self {
+            len: 0,
+            data: Data::Inline(MaybeUninit::uninit()),
+        }
+    }
+
+    pub fn with_capacity(cap: usize) -> Self {
+        let mut vec = Self::new();
+        vec.reserve(cap);
+        vec
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        match &self.data {
+            Data::Inline(_) if mem::size_of::<A::Item>() == 0 => usize::MAX,
+            Data::Inline(_) => A::CAP,
+            Data::Heap(raw) => raw.cap(),
+        }
+    }
+
+    // whether the elements are on the heap
+    pub fn spilled(&self) -> bool {
+        matches!(self.data, Data::Heap(_))
+    }
+
+    fn as_ptr(&self) -> *const A::Item {
+        match &self.data {
+            Data::Inline(buf) => buf.as_ptr() as *const A::Item,
+            Data::Heap(raw) => raw.as_ptr(),
+        }
+    }
+
+    // for writes, as_ptr only has shared access to the inline buffer
+    fn as_mut_ptr(&mut self) -> *mut A::Item {
+        match &mut self.data {
+            Data::Inline(buf) => buf.as_mut_ptr() as *mut A::Item,
+            Data::Heap(raw) => raw.as_ptr(),
+        }
+    }
+
+    pub fn reserve(&mut self, target_cap: usize) {
+        handle_reserve(self.try_reserve(target_cap));
+    }
+
+    pub fn try_reserve(&mut self, target_cap: usize) -> Result<(), TryReserveError> {
+        if target_cap <= self.capacity() {
+            return Ok(());
+        }
+
+        match &mut self.data {
+            Data::Inline(buf) => {
+                let raw = RawVec::try_with_capacity_in(target_cap, Global)?;
+                unsafe {
+                    ptr::copy_nonoverlapping(
+                        buf.as_ptr() as *const A::Item,
+                        raw.as_ptr(),
+                        self.len,
+                    );
+                }
+                self.data = Data::Heap(raw);
+                Ok(())
+            }
+            Data::Heap(raw) => raw.try_reserve(target_cap),
+        }
+    }
+
+    fn grow_one(&mut self) {
+        if self.len == self.capacity() {
+            let target_cap = self.len.checked_add(1).expect("capacity overflow");
+            self.reserve(std::cmp::max(2 * self.len, target_cap));
+        }
+    }
+
+    pub fn push(&mut self, val: A::Item) {
+        self.grow_one();
+        unsafe {
+            ptr::write(self.as_mut_ptr().add(self.len), val);
+        }
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<A::Item> {
+        if self.len == 0 {
+            None
+        } else {
+            self.len -= 1;
+            unsafe { Some(ptr::read(self.as_ptr().add(self.len))) }
+        }
+    }
+
+    pub fn insert(&mut self, idx: usize, val: A::Item) {
+        assert!(idx <= self.len, "insert index out of bounds");
+        self.grow_one();
+        unsafe {
+            let ptr = self.as_mut_ptr().add(idx);
+            ptr::copy(ptr, ptr.add(1), self.len - idx);
+            ptr::write(ptr, val);
+        }
+        self.len += 1;
+    }
+
+    pub fn remove(&mut self, idx: usize) -> A::Item {
+        assert!(idx < self.len, "remove index out of bounds");
+        unsafe {
+            let ptr = self.as_mut_ptr().add(idx);
+            let val = ptr::read(ptr);
+            ptr::copy(ptr.add(1), ptr, self.len - idx - 1);
+            self.len -= 1;
+            val
+        }
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+
+        let tail =
+            ptr::slice_from_raw_parts_mut(unsafe { self.as_mut_ptr().add(len) }, self.len - len);
+        self.len = len;
+        unsafe {
+            ptr::drop_in_place(tail);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+}
+
+impl<A: Array> Default for SmallVec<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: Array> Drop for SmallVec<A> {
+    fn drop(&mut self) {
+        // the heap buffer is freed by RawVec
+        self.clear();
+    }
+}
+
+impl<A: Array> Deref for SmallVec<A> {
+    type Target = [A::Item];
+    fn deref(&self) -> &[A::Item] {
+        unsafe { slice::from_raw_parts(self.as_ptr(), self.len) }
+    }
+}
+
+impl<A: Array> DerefMut for SmallVec<A> {
+    fn deref_mut(&mut self) -> &mut [A::Item] {
+        unsafe { slice::from_raw_parts_mut(self.as_mut_ptr(), self.len) }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SmallVec;
+    use rand::random;
+    use std::rc::Rc;
+
+    fn rand_op(my_vec: &mut SmallVec<[i32; 8]>, std_vec: &mut Vec<i32>) {
+        match random::<u32>() % 6 {
+            0 | 1 => {
+                let val = random();
+                my_vec.push(val);
+                std_vec.push(val);
+            }
+            2 => {
+                assert_eq!(my_vec.pop(), std_vec.pop());
+            }
+            3 => {
+                let idx = random::<usize>() % (my_vec.len() + 1);
+                let val = random();
+                my_vec.insert(idx, val);
+                std_vec.insert(idx, val);
+            }
+            4 => {
+                if !my_vec.is_empty() {
+                    let idx = random::<usize>() % my_vec.len();
+                    assert_eq!(my_vec.remove(idx), std_vec.remove(idx));
+                }
+            }
+            _ => {
+                if !my_vec.is_empty() {
+                    let idx = random::<usize>() % my_vec.len();
+                    let val = random();
+                    my_vec[idx] = val;
+                    std_vec[idx] = val;
+                }
+            }
+        }
+        assert_eq!(&my_vec[..], &std_vec[..]);
+    }
+
+    #[test]
+    fn inline() {
+        let mut my_vec = SmallVec::<[i32; 8]>::new();
+        for i in 0..8 {
+            my_vec.push(i);
+        }
+        assert!(!my_vec.spilled());
+        assert_eq!(my_vec.capacity(), 8);
+
+        my_vec.insert(0, -1);
+        assert!(my_vec.spilled());
+        assert_eq!(&my_vec[..], &[-1, 0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(my_vec.remove(0), -1);
+
+        assert!(SmallVec::<[i32; 8]>::with_capacity(9).spilled());
+        assert!(!SmallVec::<[(); 0]>::with_capacity(100).spilled());
+    }
+
+    #[test]
+    fn test() {
+        for _ in 0..100 {
+            let mut my_vec = SmallVec::new();
+            let mut std_vec = Vec::new();
+            for _ in 0..random::<usize>() % 100 {
+                rand_op(&mut my_vec, &mut std_vec);
+            }
+        }
+    }
+
+    #[test]
+    fn drop() {
+        let rc = Rc::new(());
+        for len in [4, 16] {
+            let mut my_vec = SmallVec::<[Rc<()>; 8]>::new();
+            for _ in 0..len {
+                my_vec.push(rc.clone());
+            }
+            my_vec.pop();
+            my_vec.remove(0);
+            assert_eq!(Rc::strong_count(&rc), len - 1);
+            std::mem::drop(my_vec);
+            assert_eq!(Rc::strong_count(&rc), 1);
+        }
+    }
+}